@@ -0,0 +1,484 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+//! configfs interface to declare devices.
+//!
+//! Each directory created under /sys/kernel/config/text_to_morse/ declares a device.
+//! A device is configured via the following attributes, before it is enabled:
+//! * overwrite: Overwrite mode, 0 to disable, 1 to enable.
+//! * broadcast: Broadcast mode, 0 to disable, 1 to enable.
+//! * multi_writer: Multi writer mode, 0 to disable, 1 to enable.
+//! * private_sessions: Private session mode, 0 to disable, 1 to enable.
+//! * separator: Prosign id of the message separator, 0 to disable.
+//! * alphabet: The alphabet used for conversion. Only "latin" is supported.
+//! * symbols: The symbols of dit and dah in the morse code read from the device, two
+//!   distinct printable ASCII characters. ".-" by default.
+//! * queue_size: Number of bytes the device is able to store, BUFFER_SIZE (256) to
+//!   MAX_QUEUE_SIZE (65536). BUFFER_SIZE by default.
+//! * wpm: Words per minute readers shall key the morse code at, 1 to MAX_WPM (60).
+//!   DEFAULT_WPM (20) by default. The device doesn't time its output, it only stores the
+//!   speed and reports it via sysfs, e.g. to the player keying the morse code.
+//! * enable: Write 1 to create the device, 0 to remove it again.
+//! * id: The id of the created device, read-only.
+//!
+//! An enabled device is accessible via /dev/morse/<directory name>.
+//! Settings can't be changed while the device is enabled. Removing the directory removes
+//! the device, even if it is still opened.
+
+use super::{
+    morse::{self, Prosign},
+    sysfs::PageWriter,
+    Context, Device, DeviceConfig, DeviceOptions, BUFFER_SIZE, MAX_QUEUE_SIZE, MAX_WPM,
+};
+use core::{ffi::c_char, fmt::Write, mem::MaybeUninit, ptr};
+use kernel::{
+    bindings, c_str, container_of,
+    prelude::*,
+    sync::{smutex::Mutex, Arc},
+};
+
+// Indices of all attributes of a device directory.
+const ATTR_OVERWRITE: usize = 0;
+const ATTR_BROADCAST: usize = 1;
+const ATTR_MULTI_WRITER: usize = 2;
+const ATTR_PRIVATE_SESSIONS: usize = 3;
+const ATTR_SEPARATOR: usize = 4;
+const ATTR_ALPHABET: usize = 5;
+const ATTR_SYMBOLS: usize = 6;
+const ATTR_QUEUE_SIZE: usize = 7;
+const ATTR_WPM: usize = 8;
+const ATTR_ENABLE: usize = 9;
+const ATTR_ID: usize = 10;
+const ATTR_COUNT: usize = 11;
+
+// Lock class of the subsystem mutex. Lockdep requires lock classes in static memory.
+static mut SUBSYSTEM_LOCK_KEY: MaybeUninit<bindings::lock_class_key> = MaybeUninit::uninit();
+
+/// configfs subsystem of the module, unregistered on drop.
+pub(crate) struct Configfs {
+    subsystem: Box<Subsystem>, // Registered subsystem
+}
+
+// SAFETY: The raw pointers within Subsystem are only modified on creation and on drop.
+// configfs synchronizes all accesses to the subsystem.
+unsafe impl Send for Configfs {}
+
+// SAFETY: Configfs offers no methods accessing the subsystem.
+unsafe impl Sync for Configfs {}
+
+// Attributes of each device directory and the NULL terminated pointers to them.
+type Attributes = [bindings::configfs_attribute; ATTR_COUNT];
+type AttributePtrs = [*mut bindings::configfs_attribute; ATTR_COUNT + 1];
+
+/// All data structures of the subsystem, referenced by configfs.
+struct Subsystem {
+    subsystem: bindings::configfs_subsystem,        // The subsystem itself
+    group_type: bindings::config_item_type,         // Type of the subsystem directory
+    group_ops: bindings::configfs_group_operations, // Creates and removes devices
+    item_type: bindings::config_item_type,          // Type of the device directories
+    item_ops: bindings::configfs_item_operations,   // Releases device directories
+    attributes: Attributes,                         // Attributes of each device
+    attribute_ptrs: AttributePtrs,                  // NULL terminated
+    context: Arc<Context>,                          // Module wide interfaces
+}
+
+impl Configfs {
+    /// Try to register the configfs subsystem.
+    ///
+    /// # Arguments:
+    /// * context: The module wide interfaces, used to create devices.
+    /// * module: The module owning the subsystem.
+    ///
+    /// # Returns:
+    /// On success, an Ok containing the registered subsystem,
+    /// on failure an Err containing one of the following error codes:
+    /// * ENOMEM: The subsystem must be allocated and this fails.
+    /// * EEXIST: The subsystem is already registered.
+    pub(crate) fn try_new(context: Arc<Context>, module: &'static ThisModule) -> Result<Self> {
+        // SAFETY: All configfs structures are plain C structs. All zeroes is a valid value.
+        let mut subsystem = Box::try_new(unsafe {
+            Subsystem {
+                subsystem: MaybeUninit::zeroed().assume_init(),
+                group_type: MaybeUninit::zeroed().assume_init(),
+                group_ops: MaybeUninit::zeroed().assume_init(),
+                item_type: MaybeUninit::zeroed().assume_init(),
+                item_ops: MaybeUninit::zeroed().assume_init(),
+                attributes: MaybeUninit::zeroed().assume_init(),
+                attribute_ptrs: [ptr::null_mut(); ATTR_COUNT + 1],
+                context,
+            }
+        })?;
+
+        let attributes: [(&CStr, u16, Option<Store>); ATTR_COUNT] = [
+            (c_str!("overwrite"), 0o644, Some(store::<ATTR_OVERWRITE>)),
+            (c_str!("broadcast"), 0o644, Some(store::<ATTR_BROADCAST>)),
+            (c_str!("multi_writer"), 0o644, Some(store::<ATTR_MULTI_WRITER>)),
+            (c_str!("private_sessions"), 0o644, Some(store::<ATTR_PRIVATE_SESSIONS>)),
+            (c_str!("separator"), 0o644, Some(store::<ATTR_SEPARATOR>)),
+            (c_str!("alphabet"), 0o644, Some(store::<ATTR_ALPHABET>)),
+            (c_str!("symbols"), 0o644, Some(store::<ATTR_SYMBOLS>)),
+            (c_str!("queue_size"), 0o644, Some(store::<ATTR_QUEUE_SIZE>)),
+            (c_str!("wpm"), 0o644, Some(store::<ATTR_WPM>)),
+            (c_str!("enable"), 0o644, Some(store::<ATTR_ENABLE>)),
+            (c_str!("id"), 0o444, None),
+        ];
+        let shows: [Show; ATTR_COUNT] = [
+            show::<ATTR_OVERWRITE>,
+            show::<ATTR_BROADCAST>,
+            show::<ATTR_MULTI_WRITER>,
+            show::<ATTR_PRIVATE_SESSIONS>,
+            show::<ATTR_SEPARATOR>,
+            show::<ATTR_ALPHABET>,
+            show::<ATTR_SYMBOLS>,
+            show::<ATTR_QUEUE_SIZE>,
+            show::<ATTR_WPM>,
+            show::<ATTR_ENABLE>,
+            show::<ATTR_ID>,
+        ];
+
+        let s = &mut *subsystem;
+        for (index, ((name, mode, store), show)) in attributes.iter().zip(shows).enumerate() {
+            let attribute = &mut s.attributes[index];
+            attribute.ca_name = name.as_char_ptr();
+            attribute.ca_owner = module.as_ptr();
+            attribute.ca_mode = *mode;
+            attribute.show = Some(show);
+            attribute.store = *store;
+            s.attribute_ptrs[index] = attribute;
+        }
+
+        s.item_ops.release = Some(release);
+        s.item_type.ct_owner = module.as_ptr();
+        s.item_type.ct_item_ops = &mut s.item_ops;
+        s.item_type.ct_attrs = s.attribute_ptrs.as_mut_ptr();
+
+        s.group_ops.make_group = Some(make_group);
+        s.group_ops.drop_item = Some(drop_item);
+        s.group_type.ct_owner = module.as_ptr();
+        s.group_type.ct_group_ops = &mut s.group_ops;
+
+        // The name must fit into ci_namebuf, including the NUL terminator.
+        let name = c_str!("text_to_morse").as_bytes_with_nul();
+        let group = &mut s.subsystem.su_group;
+        for (dst, src) in group.cg_item.ci_namebuf.iter_mut().zip(name) {
+            *dst = *src as c_char;
+        }
+        group.cg_item.ci_type = &s.group_type;
+
+        // SAFETY: The subsystem is allocated on the heap, so it never moves. The lock class
+        // key is static. Unregistration happens on drop.
+        let ret = unsafe {
+            bindings::config_group_init(group);
+            bindings::__mutex_init(
+                &mut s.subsystem.su_mutex,
+                c_str!("text_to_morse_configfs").as_char_ptr(),
+                ptr::addr_of_mut!(SUBSYSTEM_LOCK_KEY) as *mut bindings::lock_class_key,
+            );
+            bindings::configfs_register_subsystem(&mut s.subsystem)
+        };
+        if ret != 0 {
+            return Err(Error::from_kernel_errno(ret));
+        }
+
+        Ok(Self { subsystem })
+    }
+}
+
+impl Drop for Configfs {
+    fn drop(&mut self) {
+        // SAFETY: The subsystem was registered on creation and is unregistered only once.
+        // configfs holds a reference to the module while directories exist, so all device
+        // directories are removed already.
+        unsafe { bindings::configfs_unregister_subsystem(&mut self.subsystem.subsystem) };
+    }
+}
+
+/// Directory declaring a device.
+struct DeviceItem {
+    group: bindings::config_group, // configfs directory
+    state: Mutex<ItemState>,       // Settings and the created device
+    context: Arc<Context>,         // Module wide interfaces, used to create the device
+}
+
+/// Mutable state of a device directory.
+struct ItemState {
+    config: DeviceConfig,        // Settings of the device
+    options: DeviceOptions,      // Queue size, output symbols and speed
+    device: Option<Arc<Device>>, // The created device, if enabled
+}
+
+impl DeviceItem {
+    /// Get the device directory containing a configfs item
+    ///
+    /// # Safety
+    /// item must be the item of a DeviceItem, created by make_group.
+    unsafe fn from_item<'a>(item: *mut bindings::config_item) -> &'a DeviceItem {
+        // SAFETY: Guaranteed by the caller.
+        unsafe { &*container_of!(item, DeviceItem, group.cg_item) }
+    }
+
+    /// Get the name of the device directory.
+    fn name(&self) -> &CStr {
+        // SAFETY: configfs sets a NUL terminated name on creation. It never changes, since
+        // renaming directories is not supported.
+        unsafe { CStr::from_char_ptr(self.group.cg_item.ci_name) }
+    }
+
+    /// Store an attribute
+    ///
+    /// # Arguments:
+    /// * attr: The index of the attribute.
+    /// * value: The value to store, without surrounding whitespace.
+    ///
+    /// # Returns:
+    /// On success, an Ok containing the unit type,
+    /// on failure an Err containing one of the following error codes:
+    /// * EINVAL: The value is invalid for the attribute, e.g. a queue size out of range.
+    /// * EBUSY: Settings are changed while the device is enabled, or the device is
+    ///   disabled while it is still opened.
+    /// * ENOSPC: The device must be created and the maximum number of devices exist already.
+    /// * ENOMEM: The device or its queue must be allocated and this fails.
+    /// * EEXIST: The device must be created and a device node of the same name exists.
+    fn store(&self, attr: usize, value: &str) -> Result {
+        let mut state = self.state.lock();
+        if attr == ATTR_ENABLE {
+            return match (value, state.device.take()) {
+                ("1", None) => {
+                    let name = Some(self.name());
+                    let (config, options) = (&state.config, &state.options);
+                    let device = Device::try_create(&self.context, config, options, name)?;
+                    state.device = Some(device);
+                    Ok(())
+                }
+                ("0", Some(device)) => {
+                    if let Err(errno) = remove(&device, false) {
+                        state.device = Some(device);
+                        return Err(errno);
+                    }
+                    Ok(())
+                }
+                ("0", None) => Ok(()),
+                ("1", device @ Some(_)) => {
+                    state.device = device;
+                    Ok(())
+                }
+                (_, device) => {
+                    state.device = device;
+                    Err(EINVAL)
+                }
+            };
+        }
+
+        if state.device.is_some() {
+            return Err(EBUSY);
+        }
+        match (attr, value.as_bytes()) {
+            (ATTR_ALPHABET, _) if value == morse::ALPHABET => return Ok(()),
+            (ATTR_ALPHABET, _) => return Err(EINVAL),
+            (ATTR_SYMBOLS, &[dit, dah])
+                if dit != dah && dit.is_ascii_graphic() && dah.is_ascii_graphic() =>
+            {
+                state.options.symbols = [dit, dah];
+                return Ok(());
+            }
+            (ATTR_SYMBOLS, _) => return Err(EINVAL),
+            (ATTR_QUEUE_SIZE, _) => {
+                let size = value.parse::<usize>().map_err(|_| EINVAL)?;
+                if !(BUFFER_SIZE..=MAX_QUEUE_SIZE).contains(&size) {
+                    return Err(EINVAL);
+                }
+                state.options.queue_size = size;
+                return Ok(());
+            }
+            _ => (),
+        }
+
+        let value = value.parse::<u32>().map_err(|_| EINVAL)?;
+        match attr {
+            ATTR_OVERWRITE => state.config.overwrite = (value != 0) as u32,
+            ATTR_BROADCAST => state.config.broadcast = (value != 0) as u32,
+            ATTR_MULTI_WRITER => state.config.multi_writer = (value != 0) as u32,
+            ATTR_PRIVATE_SESSIONS => state.config.private_sessions = (value != 0) as u32,
+            ATTR_SEPARATOR => {
+                if value != 0 && Prosign::from_id(value).is_none() {
+                    return Err(EINVAL);
+                }
+                state.config.separator = value;
+            }
+            ATTR_WPM => {
+                if !(1..=MAX_WPM).contains(&value) {
+                    return Err(EINVAL);
+                }
+                state.options.wpm = value;
+            }
+            _ => return Err(EINVAL),
+        }
+        Ok(())
+    }
+}
+
+/// Remove a device created by a device directory
+///
+/// # Arguments:
+/// * device: The device to remove.
+/// * force: Remove the device even if it is not idle.
+///
+/// # Returns:
+/// On success, an Ok containing the unit type, on failure an Err containing return code
+/// EBUSY if the device is not idle and force is not set.
+///
+/// # Notes:
+/// The device might have been removed via the control device already. In this case,
+/// the device with the same id is a different one and must not be removed.
+fn remove(device: &Arc<Device>, force: bool) -> Result {
+    match Device::get(device.id) {
+        Ok(pooled) if ptr::eq(&*pooled, &**device) => Device::remove(device.id, force),
+        _ => Ok(()),
+    }
+}
+
+// Signature of all show callbacks.
+type Show = unsafe extern "C" fn(*mut bindings::config_item, *mut c_char) -> bindings::ssize_t;
+
+// Signature of all store callbacks.
+type Store =
+    unsafe extern "C" fn(*mut bindings::config_item, *const c_char, usize) -> bindings::ssize_t;
+
+/// Show callback of all device attributes
+///
+/// # Arguments:
+/// * ATTR: The index of the attribute to show.
+/// * item: The device directory the attribute belongs to.
+/// * page: Buffer of PAGE_SIZE bytes to write the attribute value into.
+///
+/// # Returns:
+/// The number of bytes written into page.
+unsafe extern "C" fn show<const ATTR: usize>(
+    item: *mut bindings::config_item,
+    page: *mut c_char,
+) -> bindings::ssize_t {
+    // SAFETY: Attributes are only added to device directories.
+    let item = unsafe { DeviceItem::from_item(item) };
+    let state = item.state.lock();
+    let config = &state.config;
+
+    let mut page = PageWriter {
+        buf: page as *mut u8,
+        pos: 0,
+    };
+    let _ = match ATTR {
+        ATTR_OVERWRITE => writeln!(page, "{}", config.overwrite),
+        ATTR_BROADCAST => writeln!(page, "{}", config.broadcast),
+        ATTR_MULTI_WRITER => writeln!(page, "{}", config.multi_writer),
+        ATTR_PRIVATE_SESSIONS => writeln!(page, "{}", config.private_sessions),
+        ATTR_SEPARATOR => writeln!(page, "{}", config.separator),
+        ATTR_ALPHABET => writeln!(page, "{}", morse::ALPHABET),
+        ATTR_SYMBOLS => {
+            let [dit, dah] = state.options.symbols;
+            writeln!(page, "{}{}", dit as char, dah as char)
+        }
+        ATTR_QUEUE_SIZE => writeln!(page, "{}", state.options.queue_size),
+        ATTR_WPM => writeln!(page, "{}", state.options.wpm),
+        ATTR_ENABLE => writeln!(page, "{}", state.device.is_some() as u32),
+        _ => match &state.device {
+            Some(device) => writeln!(page, "{}", device.id),
+            None => writeln!(page, "none"),
+        },
+    };
+    page.pos as bindings::ssize_t
+}
+
+/// Store callback of all writable device attributes
+///
+/// # Arguments:
+/// * ATTR: The index of the attribute to store.
+/// * item: The device directory the attribute belongs to.
+/// * page: Buffer containing the new value.
+/// * count: The number of bytes in page.
+///
+/// # Returns:
+/// On success, count, on failure a negative error code. See DeviceItem::store.
+unsafe extern "C" fn store<const ATTR: usize>(
+    item: *mut bindings::config_item,
+    page: *const c_char,
+    count: usize,
+) -> bindings::ssize_t {
+    // SAFETY: Attributes are only added to device directories.
+    let item = unsafe { DeviceItem::from_item(item) };
+
+    // SAFETY: configfs passes a buffer containing count bytes.
+    let bytes = unsafe { core::slice::from_raw_parts(page as *const u8, count) };
+    let result = core::str::from_utf8(bytes)
+        .map_err(|_| EINVAL)
+        .and_then(|value| item.store(ATTR, value.trim()));
+
+    match result {
+        Ok(()) => count as bindings::ssize_t,
+        Err(errno) => errno.to_kernel_errno() as bindings::ssize_t,
+    }
+}
+
+/// Create a device directory. Called on mkdir.
+///
+/// # Arguments:
+/// * group: The subsystem directory.
+/// * name: The name of the new directory.
+///
+/// # Returns:
+/// On success, the new directory, on failure ENOMEM as error pointer.
+unsafe extern "C" fn make_group(
+    group: *mut bindings::config_group,
+    name: *const c_char,
+) -> *mut bindings::config_group {
+    // SAFETY: Device directories are only created within the subsystem directory.
+    let subsystem = unsafe { &*container_of!(group, Subsystem, subsystem.su_group) };
+
+    let item = Box::try_new(DeviceItem {
+        // SAFETY: config_group is a plain C struct. All zeroes is a valid value.
+        group: unsafe { MaybeUninit::zeroed().assume_init() },
+        state: Mutex::new(ItemState {
+            config: DeviceConfig::from_params(),
+            options: DeviceOptions::default(),
+            device: None,
+        }),
+        context: subsystem.context.clone(),
+    });
+    let item = match item {
+        Ok(item) => Box::into_raw(item),
+        Err(_) => return ENOMEM.to_kernel_errno() as isize as *mut bindings::config_group,
+    };
+
+    // SAFETY: item was just allocated and is freed by release. name is copied.
+    unsafe {
+        bindings::config_group_init_type_name(&mut (*item).group, name, &subsystem.item_type);
+        &mut (*item).group
+    }
+}
+
+/// Remove a device directory. Called on rmdir.
+///
+/// # Arguments:
+/// * _group: The subsystem directory.
+/// * item: The directory to remove.
+unsafe extern "C" fn drop_item(
+    _group: *mut bindings::config_group,
+    item: *mut bindings::config_item,
+) {
+    // SAFETY: Only device directories are created within the subsystem directory.
+    let device_item = unsafe { DeviceItem::from_item(item) };
+    if let Some(device) = device_item.state.lock().device.take() {
+        let _ = remove(&device, true);
+    }
+
+    // SAFETY: Drops the reference taken on creation. The last reference calls release.
+    unsafe { bindings::config_item_put(item) };
+}
+
+/// Free a device directory, after the last reference to it was dropped.
+///
+/// # Arguments:
+/// * item: The directory to free.
+unsafe extern "C" fn release(item: *mut bindings::config_item) {
+    // SAFETY: item belongs to a DeviceItem allocated by make_group. It is not used anymore.
+    drop(unsafe { Box::from_raw(DeviceItem::from_item(item) as *const _ as *mut DeviceItem) });
+}
@@ -0,0 +1,161 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+//! Control device to create and remove devices at runtime.
+//!
+//! The control device is available as /dev/text_to_morse_ctl. Each created device is
+//! accessible via /dev/morse/<id>, e.g. /dev/morse/5.
+
+use super::{ioctl::IOCTL_TYPE, morse::Prosign, Context, Device, DeviceConfig, DeviceOptions};
+use kernel::{
+    file::{self, File, IoctlCommand, IoctlHandler},
+    io_buffer::{IoBufferReader, IoBufferWriter},
+    ioctl::{_IOW, _IOWR},
+    prelude::*,
+    sync::Arc,
+    user_ptr::{UserSlicePtr, UserSlicePtrReader},
+    ForeignOwnable,
+};
+
+/// Create a new device. Argument: struct of 6 u32 values in the following order:
+/// id, overwrite, broadcast, multi_writer, private_sessions, separator. The id is ignored
+/// on input and set to the id of the new device on output. All other values are the
+/// settings of the new device, see the ioctls of the same names.
+pub(crate) const IOCTL_CREATE_DEVICE: u32 = _IOWR::<DeviceConfig>(IOCTL_TYPE, 0x20);
+
+/// Remove an idle device. Argument: u32, the id of the device to remove.
+pub(crate) const IOCTL_REMOVE_DEVICE: u32 = _IOW::<u32>(IOCTL_TYPE, 0x21);
+
+/// Control device creating and removing devices.
+pub(crate) struct Control;
+
+#[vtable]
+impl file::Operations for Control {
+    type OpenData = Arc<Context>;
+    type Data = Arc<Context>;
+
+    /// Syscall open implementation
+    ///
+    /// # Arguments:
+    /// * context: The module wide interfaces, given on registration.
+    /// * _file: Reference kernel file data structure.
+    ///
+    /// # Returns:
+    /// An Ok containing the module wide interfaces.
+    fn open(context: &Self::OpenData, _file: &File) -> Result<Self::Data> {
+        Ok(context.clone())
+    }
+
+    /// Syscall ioctl implementation
+    ///
+    /// # Arguments:
+    /// * context: The module wide interfaces.
+    /// * file: Reference kernel file data structure.
+    /// * cmd: The ioctl command to handle.
+    ///
+    /// # Returns:
+    /// On success: An Ok containing 0, on failure an Err containing one of
+    /// the following error codes:
+    /// * ENOTTY: The given command is not supported.
+    /// * EFAULT: Data could not be transferred from/to user space.
+    /// * See IoctlHandler implementation of Context for command specific errors.
+    fn ioctl(
+        context: <Self::Data as ForeignOwnable>::Borrowed<'_>,
+        file: &File,
+        cmd: &mut IoctlCommand,
+    ) -> Result<i32> {
+        cmd.dispatch::<Context>(&context, file)
+    }
+}
+
+impl IoctlHandler for Context {
+    type Target<'a> = &'a Context;
+
+    /// Handle ioctl commands transferring data from user space to the control device.
+    ///
+    /// # Arguments:
+    /// * _context: The module wide interfaces.
+    /// * _file: Reference kernel file data structure.
+    /// * cmd: The ioctl command to handle.
+    /// * reader: Reader to transfer the given data from user space.
+    ///
+    /// # Returns:
+    /// On success: An Ok containing 0, on failure an Err containing one of
+    /// the following error codes:
+    /// * ENOTTY: The given command is not supported.
+    /// * ENODEV: There is no device with the given id.
+    /// * EBUSY: The device to remove is opened by at least one file.
+    /// * EFAULT: Data could not be transferred from user space.
+    fn write(
+        _context: &Context,
+        _file: &File,
+        cmd: u32,
+        reader: &mut UserSlicePtrReader,
+    ) -> Result<i32> {
+        match cmd {
+            IOCTL_REMOVE_DEVICE => {
+                let id = u16::try_from(reader.read::<u32>()?).map_err(|_| ENODEV)?;
+                if let Err(errno) = Device::remove(id, false) {
+                    log_err!("Failed to remove device {}. Error was: {:?}\n", id, errno);
+                    return Err(errno);
+                }
+                log_info!("Removed device {}.\n", id);
+            }
+            _ => {
+                log_err!("Unsupported ioctl command {:#x}.\n", cmd);
+                return Err(ENOTTY);
+            }
+        }
+        Ok(0)
+    }
+
+    /// Handle ioctl commands transferring data from and to user space.
+    ///
+    /// # Arguments:
+    /// * context: The module wide interfaces.
+    /// * _file: Reference kernel file data structure.
+    /// * cmd: The ioctl command to handle.
+    /// * data: User space buffer to transfer data from and to.
+    ///
+    /// # Returns:
+    /// On success: An Ok containing 0, on failure an Err containing one of
+    /// the following error codes:
+    /// * ENOTTY: The given command is not supported.
+    /// * EINVAL: The given separator is no known prosign id.
+    /// * ENOSPC: The maximum number of devices exist already.
+    /// * ENOMEM: The device must be allocated and this fails.
+    /// * EFAULT: Data could not be transferred from/to user space.
+    fn read_write(context: &Context, _file: &File, cmd: u32, data: UserSlicePtr) -> Result<i32> {
+        match cmd {
+            IOCTL_CREATE_DEVICE => {
+                let (mut reader, mut writer) = data.reader_writer();
+                let mut config = reader.read::<DeviceConfig>()?;
+                if config.separator != 0 && Prosign::from_id(config.separator).is_none() {
+                    return Err(EINVAL);
+                }
+
+                let options = DeviceOptions::default();
+                let device = match Device::try_create(context, &config, &options, None) {
+                    Ok(device) => device,
+                    Err(errno) => {
+                        log_err!("Failed to create device. Error was: {:?}\n", errno);
+                        return Err(errno);
+                    }
+                };
+
+                // Without the id, the device is unusable. Remove it again on failure.
+                config.id = device.id as u32;
+                if let Err(errno) = writer.write(&config) {
+                    let _ = Device::remove(device.id, false);
+                    return Err(errno);
+                }
+                log_info!("Created device {}.\n", device.id);
+            }
+            _ => {
+                log_err!("Unsupported ioctl command {:#x}.\n", cmd);
+                return Err(ENOTTY);
+            }
+        }
+        Ok(0)
+    }
+}
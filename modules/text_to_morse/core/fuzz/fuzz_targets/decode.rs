@@ -0,0 +1,97 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+//! Decode arbitrary bytes sequence by sequence, like try_read_char does on each write.
+//! The bytes are decoded as UTF-8 and as UTF-16.
+//!
+//! Invariants:
+//! * Decoding never panics.
+//! * The sequences cover all bytes, each byte is decoded exactly once.
+//! * Valid sequences are the characters of std. Invalid sequences are the maximal subparts
+//!   std replaces with U+FFFD. Only the last sequence may be truncated.
+//! * In UTF-16, sequences are code units std replaces with U+FFFD, or valid characters.
+//!   An odd trailing byte is truncated.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use text_to_morse_core::{
+    encoding::{self, Encoding},
+    utf8::{DecodeError, Decoder},
+};
+
+/// Decode bytes as UTF-16 and compare with std.
+fn check_utf16(data: &[u8]) {
+    let mut bytes = data.iter().copied();
+    let mut decoder = encoding::Decoder::new(Encoding::Utf16, None);
+    let mut consumed = 0usize;
+    let mut lossy = String::new();
+    let mut truncated = false;
+    while let Some(sequence) = decoder.decode(|| bytes.next()) {
+        assert!(!truncated);
+        assert!(0 < sequence.len && sequence.len <= 4);
+        consumed += sequence.len;
+        match sequence.char {
+            Ok(char) => {
+                assert_eq!(sequence.len, 2 * char.len_utf16());
+                lossy.push(char);
+            }
+            Err(error) => {
+                truncated = error == DecodeError::Truncated;
+                lossy.push(char::REPLACEMENT_CHARACTER);
+            }
+        }
+    }
+
+    assert_eq!(consumed, data.len());
+    assert_eq!(decoder.pending(), 0);
+    let big_endian = data.starts_with(&[0xfe, 0xff]);
+    let units = data.chunks_exact(2).map(|unit| match big_endian {
+        true => u16::from_be_bytes([unit[0], unit[1]]),
+        false => u16::from_le_bytes([unit[0], unit[1]]),
+    });
+    let chars: Vec<_> = char::decode_utf16(units).collect();
+    let mut expected: String = chars
+        .iter()
+        .map(|char| char.clone().unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect();
+
+    // An odd trailing byte is truncated with an unpaired high surrogate in front of it.
+    let high_surrogate = matches!(
+        chars.last(),
+        Some(Err(error)) if (0xd800..0xdc00).contains(&error.unpaired_surrogate())
+    );
+    if data.len() % 2 == 1 && !high_surrogate {
+        expected.push(char::REPLACEMENT_CHARACTER);
+    }
+    assert_eq!(lossy, expected);
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut bytes = data.iter().copied();
+    let mut decoder = Decoder::new();
+    let mut consumed = 0usize;
+    let mut lossy = String::new();
+    let mut truncated = false;
+    while let Some(sequence) = decoder.decode(|| bytes.next()) {
+        assert!(!truncated);
+        assert!(0 < sequence.len && sequence.len <= 4);
+        consumed += sequence.len;
+        match sequence.char {
+            Ok(char) => {
+                assert_eq!(sequence.len, char.len_utf8());
+                lossy.push(char);
+            }
+            Err(error) => {
+                truncated = error == DecodeError::Truncated;
+                lossy.push(char::REPLACEMENT_CHARACTER);
+            }
+        }
+    }
+
+    assert_eq!(consumed, data.len());
+    assert_eq!(decoder.pending(), 0);
+    assert_eq!(lossy, String::from_utf8_lossy(data));
+
+    check_utf16(data);
+});
@@ -0,0 +1,261 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+//! Feed arbitrary writes, interleaved with reads, through a model of Device::write.
+//!
+//! The model uses the conversion core exactly like Device::write and mirrors how it stores
+//! chunks into the queue. A writer that would block lets the reader drain the queue first.
+//!
+//! Invariants:
+//! * Nothing panics, including the unwrap after storing a chunk without overwrite.
+//! * Each write consumes its bytes up to the first invalid UTF-8 sequence, or up to a
+//!   truncated sequence at the end if invalid sequences are replaced or skipped. Writes
+//!   without anything to consume fail. In multi writer mode, a write consumes a prefix of it.
+//! * Queue accounting is consistent: All stored bytes are either read, dropped or queued.
+//! * The read morse code equals the morse code of the consumed characters, if nothing was
+//!   dropped. The queue always starts with a complete letter.
+//! * Letters are read as a whole, in order. A partially read letter dropped from the queue
+//!   is still read completely.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use core::convert::Infallible;
+use libfuzzer_sys::fuzz_target;
+use text_to_morse_core::{
+    compress::Compressor,
+    convert::{convert, Stop},
+    encoding::BOM,
+    morse::{is_letter_end, morse_code_from, MAX_PROSIGN_LEN},
+    normalize::Normalizer,
+    ringbuffer::Ringbuffer,
+    utf8::{Decoder, Policy},
+};
+
+// Same sizes as the kernel module.
+const BUFFER_SIZE: usize = 256;
+const CHUNK_SIZE: usize = BUFFER_SIZE - MAX_PROSIGN_LEN;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    overwrite: bool,       // Overwrite mode of the device
+    multi_writer: bool,    // Multi writer mode, each write stores a single chunk
+    policy: u8,            // Id of the policy handling invalid UTF-8, modulo 3
+    accesses: Vec<Access>, // Writes and reads, in order
+}
+
+#[derive(Arbitrary, Debug)]
+struct Access {
+    bytes: Vec<u8>, // Bytes passed to a write
+    read: u16,      // Number of bytes read afterwards
+}
+
+/// Model of a device, accessed by a single writer and a single reader.
+struct Device {
+    queue: Ringbuffer<u8, BUFFER_SIZE>, // Stored morse code
+    overwrite: bool,                    // Overwrite mode
+    multi_writer: bool,                 // Multi writer mode
+    policy: Policy,                     // Handling of invalid UTF-8
+    stored: usize,                      // Total number of stored bytes
+    dropped: usize,                     // Total number of bytes dropped before they were read
+    read_in_letter: usize,              // Number of read bytes of the oldest letter in queue
+    rest: Vec<u8>,                      // Unread rest of a dropped, partially read letter
+    expected: Vec<u8>,                  // Morse code of all consumed characters
+    received: Vec<u8>,                  // Morse code read by the reader
+}
+
+impl Device {
+    /// Write bytes, like Device::write. Returns None where Device::write fails with EINVAL.
+    fn write(&mut self, bytes: &[u8]) -> Option<usize> {
+        let mut bytes = bytes.iter().copied();
+        let mut decoder = Decoder::new();
+        let mut total_bytes_read = 0usize;
+        let mut drained = false;
+        while !drained {
+            let mut morse_code = Vec::new();
+            let read_char = || decoder.decode(|| bytes.next());
+            let store = |_, code: &str| {
+                morse_code.extend_from_slice(code.as_bytes());
+                Ok::<_, Infallible>(())
+            };
+            let normalizer = &mut Normalizer::default();
+            let compressor = &mut Compressor::new(None, false);
+            let conversion = convert(
+                CHUNK_SIZE,
+                self.policy,
+                normalizer,
+                compressor,
+                read_char,
+                store,
+            )
+            .unwrap();
+            assert_eq!(conversion.code_len, morse_code.len());
+            assert!(morse_code.len() <= CHUNK_SIZE);
+
+            if conversion.stop != Stop::Full {
+                if total_bytes_read + conversion.bytes_read == 0 {
+                    return None;
+                }
+                drained = true;
+            }
+            if morse_code.is_empty() {
+                // Only skipped invalid sequences and BOMs were read.
+                total_bytes_read += conversion.bytes_read;
+                break;
+            }
+
+            // A blocked writer waits until the reader made space.
+            if !self.overwrite && self.queue.free() < morse_code.len() {
+                self.read(usize::MAX);
+            }
+
+            if self.overwrite {
+                for byte in morse_code.iter() {
+                    if self.queue.is_full() {
+                        self.drop_letter();
+                    }
+                    self.queue.push_overwrite(*byte);
+                }
+            } else {
+                morse_code
+                    .iter()
+                    .try_for_each(|byte| self.queue.try_push(*byte))
+                    .unwrap(); // Due to the previous check, it should never fail.
+            }
+            self.stored += morse_code.len();
+            self.expected.extend_from_slice(&morse_code);
+            total_bytes_read += conversion.bytes_read;
+
+            if self.multi_writer {
+                break;
+            }
+        }
+        Some(total_bytes_read)
+    }
+
+    /// Drop the oldest letter from the queue, like DeviceInner::push_overwrite. The unread
+    /// rest of a partially read letter is kept.
+    fn drop_letter(&mut self) {
+        let mut letter = Vec::new();
+        while let Ok(byte) = self.queue.try_pop() {
+            letter.push(byte);
+            if is_letter_end(byte) {
+                break;
+            }
+        }
+        if self.read_in_letter > 0 {
+            self.rest.extend_from_slice(&letter[self.read_in_letter..]);
+        } else {
+            self.dropped += letter.len();
+        }
+        self.read_in_letter = 0;
+    }
+
+    /// Read up to count bytes. A letter is removed from the queue after it was read completely.
+    fn read(&mut self, count: usize) {
+        let kept = count.min(self.rest.len());
+        self.received.extend(self.rest.drain(..kept));
+        for _ in kept..count {
+            let Some(byte) = self.queue.get(self.read_in_letter).copied() else {
+                break;
+            };
+            self.received.push(byte);
+            self.read_in_letter += 1;
+            if is_letter_end(byte) {
+                self.queue.pop_until(|byte| is_letter_end(*byte));
+                self.read_in_letter = 0;
+            }
+        }
+    }
+
+    /// Check the queue accounting.
+    fn check(&self) {
+        assert_eq!(
+            self.stored,
+            self.received.len() + self.dropped + self.queue.len() + self.rest.len()
+                - self.read_in_letter
+        );
+        assert!(self
+            .expected
+            .ends_with(&self.queue.iter().copied().collect::<Vec<_>>()));
+
+        let start = self.expected.len() - self.queue.len();
+        assert!(start == 0 || is_letter_end(self.expected[start - 1]));
+        if self.dropped == 0 {
+            assert!(self.expected.starts_with(&self.received));
+        }
+
+        // Each completely read letter is one of the stored letters, in order.
+        let mut expected = self.expected.split_inclusive(|byte| is_letter_end(*byte));
+        for letter in self
+            .received
+            .split_inclusive(|byte| is_letter_end(*byte))
+            .filter(|letter| letter.last().copied().is_some_and(is_letter_end))
+        {
+            assert!(expected.any(|expected| expected == letter));
+        }
+    }
+}
+
+/// Get the bytes a write consumes at most and the characters it converts, based on std.
+/// BOMs are consumed, but not converted.
+fn consumable(bytes: &[u8], policy: Policy) -> (usize, String) {
+    let mut consumed = 0usize;
+    let mut chars = String::new();
+    for chunk in bytes.utf8_chunks() {
+        consumed += chunk.valid().len();
+        chars.extend(chunk.valid().chars().filter(|char| *char != BOM));
+
+        // The rest starts with the invalid bytes. It is truncated, if it ends within them.
+        let invalid = chunk.invalid();
+        let truncated = matches!(
+            core::str::from_utf8(&bytes[consumed..]),
+            Err(error) if error.error_len().is_none()
+        );
+        if invalid.is_empty() || truncated || policy == Policy::Fail {
+            break;
+        }
+        consumed += invalid.len();
+        if policy == Policy::Replace {
+            chars.push(char::REPLACEMENT_CHARACTER);
+        }
+    }
+    (consumed, chars)
+}
+
+fuzz_target!(|input: Input| {
+    let mut device = Device {
+        queue: Ringbuffer::new(),
+        overwrite: input.overwrite,
+        multi_writer: input.multi_writer,
+        policy: Policy::from_id(u32::from(input.policy % 3)).unwrap(),
+        stored: 0,
+        dropped: 0,
+        read_in_letter: 0,
+        rest: Vec::new(),
+        expected: Vec::new(),
+        received: Vec::new(),
+    };
+
+    for access in input.accesses.iter() {
+        let (consumable, chars) = consumable(&access.bytes, device.policy);
+
+        let expected_len = device.expected.len();
+        match device.write(&access.bytes) {
+            None => assert_eq!(consumable, 0),
+            Some(written) if device.multi_writer => assert!(0 < written && written <= consumable),
+            Some(written) => assert_eq!(written, consumable),
+        }
+
+        // The stored morse code is the morse code of the consumed characters.
+        let written = &device.expected[expected_len..];
+        let morse_code: Vec<u8> = chars
+            .chars()
+            .flat_map(|char| morse_code_from(char).bytes())
+            .collect();
+        assert!(morse_code.starts_with(written));
+
+        device.read(access.read as usize);
+        device.check();
+    }
+});
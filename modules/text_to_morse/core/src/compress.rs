@@ -0,0 +1,433 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+/// Maximum number of entries of a Dictionary.
+pub const MAX_ENTRIES: usize = 32;
+
+/// Maximum length of a phrase in a Dictionary.
+pub const MAX_PHRASE_LEN: usize = 24;
+
+/// Maximum length of an abbreviation in a Dictionary.
+pub const MAX_ABBREV_LEN: usize = 8;
+
+/// Dictionary used by default, containing common abbreviations and Q-codes.
+pub static DEFAULT_DICTIONARY: Dictionary = Dictionary::builtin();
+
+/// Errors of Dictionary::try_add.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DictionaryError {
+    Full,    // The dictionary has no space for another entry
+    Invalid, // The phrase or abbreviation is malformed
+}
+
+/// A phrase and the abbreviation replacing it.
+#[derive(Clone, Copy)]
+struct Entry {
+    phrase: [u8; MAX_PHRASE_LEN],       // Lower case phrase, zero padded
+    phrase_len: usize,                  // Length of the phrase
+    abbreviation: [u8; MAX_ABBREV_LEN], // Upper case abbreviation, zero padded
+    abbreviation_len: usize,            // Length of the abbreviation
+}
+
+impl Entry {
+    const EMPTY: Self = Self {
+        phrase: [0; MAX_PHRASE_LEN],
+        phrase_len: 0,
+        abbreviation: [0; MAX_ABBREV_LEN],
+        abbreviation_len: 0,
+    };
+
+    /// Create a new Entry from valid and normalized byte strings.
+    const fn new(phrase: &[u8], abbreviation: &[u8]) -> Self {
+        let mut entry = Self::EMPTY;
+        let mut index = 0;
+        while index < phrase.len() {
+            entry.phrase[index] = phrase[index];
+            index += 1;
+        }
+        index = 0;
+        while index < abbreviation.len() {
+            entry.abbreviation[index] = abbreviation[index];
+            index += 1;
+        }
+        entry.phrase_len = phrase.len();
+        entry.abbreviation_len = abbreviation.len();
+        entry
+    }
+
+    /// Get the phrase of the entry
+    fn phrase(&self) -> &[u8] {
+        &self.phrase[..self.phrase_len]
+    }
+
+    /// Get the abbreviation of the entry
+    fn abbreviation(&self) -> &[u8] {
+        &self.abbreviation[..self.abbreviation_len]
+    }
+
+    /// Check if the phrase starts with the given characters, ignoring their case
+    fn starts_with(&self, chars: &[(char, usize)]) -> bool {
+        self.phrase_len >= chars.len()
+            && chars
+                .iter()
+                .zip(self.phrase())
+                .all(|((char, _), byte)| char.to_ascii_lowercase() == char::from(*byte))
+    }
+}
+
+/// Phrases and the abbreviations replacing them.
+pub struct Dictionary {
+    entries: [Entry; MAX_ENTRIES], // Entries, the first len are used
+    len: usize,                    // Number of used entries
+}
+
+impl Dictionary {
+    /// Create an empty Dictionary
+    pub const fn new() -> Self {
+        Self {
+            entries: [Entry::EMPTY; MAX_ENTRIES],
+            len: 0,
+        }
+    }
+
+    /// Create the Dictionary of common abbreviations and Q-codes. See DEFAULT_DICTIONARY.
+    const fn builtin() -> Self {
+        let mut dictionary = Self::new();
+        let entries: [(&[u8], &[u8]); 17] = [
+            (b"best regards", b"73"),
+            (b"thanks", b"TNX"),
+            (b"thank you", b"TU"),
+            (b"please", b"PSE"),
+            (b"message", b"MSG"),
+            (b"report", b"RPT"),
+            (b"weather", b"WX"),
+            // Q-codes
+            (b"my location is", b"QTH"),
+            (b"i am busy", b"QRL"),
+            (b"interference", b"QRM"),
+            (b"static", b"QRN"),
+            (b"send slower", b"QRS"),
+            (b"stop sending", b"QRT"),
+            (b"who is calling me", b"QRZ"),
+            (b"i acknowledge receipt", b"QSL"),
+            (b"change frequency", b"QSY"),
+            (b"i am ready", b"QRV"),
+        ];
+        while dictionary.len < entries.len() {
+            let (phrase, abbreviation) = entries[dictionary.len];
+            dictionary.entries[dictionary.len] = Entry::new(phrase, abbreviation);
+            dictionary.len += 1;
+        }
+        dictionary
+    }
+
+    /// Try to add an entry
+    ///
+    /// # Arguments
+    /// * phrase: The phrase to replace. ASCII words of letters and digits, separated by
+    ///   single spaces. It is matched regardless of its case.
+    /// * abbreviation: The ASCII letters and digits replacing the phrase. It must not be
+    ///   longer than the phrase.
+    ///
+    /// # Returns
+    /// On success, an Ok containing the unit type. On failure, an Err containing one of the
+    /// following errors:
+    /// * DictionaryError::Full: The dictionary contains MAX_ENTRIES entries.
+    /// * DictionaryError::Invalid: The phrase or abbreviation is malformed or too long.
+    ///
+    /// # Notes
+    /// An entry with the same phrase is replaced.
+    pub fn try_add(&mut self, phrase: &[u8], abbreviation: &[u8]) -> Result<(), DictionaryError> {
+        let words_valid = phrase
+            .split(|byte| *byte == b' ')
+            .all(|word| !word.is_empty() && word.iter().all(u8::is_ascii_alphanumeric));
+        let abbreviation_valid = !abbreviation.is_empty()
+            && abbreviation.len() <= MAX_ABBREV_LEN
+            && abbreviation.len() <= phrase.len()
+            && abbreviation.iter().all(u8::is_ascii_alphanumeric);
+        if !words_valid || phrase.len() > MAX_PHRASE_LEN || !abbreviation_valid {
+            return Err(DictionaryError::Invalid);
+        }
+
+        let mut entry = Entry::new(phrase, abbreviation);
+        entry.phrase.make_ascii_lowercase();
+        entry.abbreviation.make_ascii_uppercase();
+
+        let used = &mut self.entries[..self.len];
+        match used.iter_mut().find(|used| used.phrase() == entry.phrase()) {
+            Some(used) => *used = entry,
+            None if self.len < MAX_ENTRIES => {
+                self.entries[self.len] = entry;
+                self.len += 1;
+            }
+            None => return Err(DictionaryError::Full),
+        }
+        Ok(())
+    }
+
+    /// Remove all entries
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Get the number of entries
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if the dictionary has no entries
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterate over all entries
+    ///
+    /// # Returns
+    /// An iterator over the phrase and abbreviation of each entry, in order of their addition.
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], &[u8])> {
+        self.entries[..self.len]
+            .iter()
+            .map(|entry| (entry.phrase(), entry.abbreviation()))
+    }
+
+    /// Check if a phrase starts with the given characters
+    fn is_prefix(&self, chars: &[(char, usize)]) -> bool {
+        self.entries[..self.len]
+            .iter()
+            .any(|entry| entry.starts_with(chars))
+    }
+
+    /// Lookup the abbreviation of a phrase equal to the given characters
+    fn lookup(&self, chars: &[(char, usize)]) -> Option<&[u8]> {
+        self.entries[..self.len]
+            .iter()
+            .find(|entry| entry.phrase_len == chars.len() && entry.starts_with(chars))
+            .map(Entry::abbreviation)
+    }
+}
+
+impl Clone for Dictionary {
+    fn clone(&self) -> Self {
+        let mut dictionary = Self::new();
+        dictionary.clone_from(self);
+        dictionary
+    }
+
+    /// Copy only the used entries, in place. Dictionaries are large, so this avoids a
+    /// temporary copy, e.g. on the kernel stack.
+    fn clone_from(&mut self, source: &Self) {
+        self.entries[..source.len].copy_from_slice(&source.entries[..source.len]);
+        self.len = source.len;
+    }
+}
+
+impl Default for Dictionary {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// Check if a character is part of a word. Phrases start and end at word boundaries.
+fn is_word(char: char) -> bool {
+    char.is_alphanumeric()
+}
+
+/// Characters and the number of bytes they were read from, up to a phrase and one more.
+type Buffer = [(char, usize); MAX_PHRASE_LEN + 1];
+
+/// Compressor of a stream of characters, replacing phrases with their abbreviations.
+///
+/// Characters possibly starting a phrase are held back, until the phrase is complete or
+/// ruled out. Each character is passed along with the number of bytes it was read from.
+/// The bytes of a replaced phrase are attributed to the last character of its abbreviation.
+pub struct Compressor<'a> {
+    dictionary: Option<&'a Dictionary>, // Phrases to replace, None if disabled
+    chars: Buffer,                      // Held characters, followed by unscanned ones
+    held: usize,                        // Number of held characters
+    len: usize,                         // Number of held and unscanned characters
+    output: Buffer,                     // Released characters
+    output_start: usize,                // Index of the next released character
+    output_len: usize,                  // Number of released characters
+    word: bool,                         // The last scanned character is part of a word
+}
+
+impl<'a> Compressor<'a> {
+    /// Create a new Compressor
+    ///
+    /// # Arguments
+    /// * dictionary: The phrases to replace. None passes all characters along as is.
+    /// * word: Flag if previous text of the stream ended within a word.
+    pub const fn new(dictionary: Option<&'a Dictionary>, word: bool) -> Self {
+        Self {
+            dictionary,
+            chars: [('\0', 0); MAX_PHRASE_LEN + 1],
+            held: 0,
+            len: 0,
+            output: [('\0', 0); MAX_PHRASE_LEN + 1],
+            output_start: 0,
+            output_len: 0,
+            word,
+        }
+    }
+
+    /// Check if the last character passed to the compressor is part of a word
+    pub fn word(&self) -> bool {
+        self.word
+    }
+
+    /// Get the number of characters held back
+    pub fn held(&self) -> usize {
+        self.held
+    }
+
+    /// Pass the next character to the compressor
+    ///
+    /// # Arguments
+    /// * char: The next character of the stream.
+    /// * len: The number of bytes the character was read from.
+    ///
+    /// # Notes
+    /// Released characters must be taken via pop, before the next character is passed.
+    pub fn push(&mut self, char: char, len: usize) {
+        let dictionary = match self.dictionary {
+            Some(dictionary) => dictionary,
+            None => {
+                self.release(char, len);
+                self.word = is_word(char);
+                return;
+            }
+        };
+
+        self.chars[self.len] = (char, len);
+        self.len += 1;
+        self.scan(dictionary);
+    }
+
+    /// Pass bytes to the compressor, which were read without a character
+    ///
+    /// # Arguments
+    /// * len: The number of bytes read.
+    ///
+    /// # Returns
+    /// The number of bytes to count right away. Zero if they are attributed to the last
+    /// held character instead.
+    pub fn skip(&mut self, len: usize) -> usize {
+        if self.held == 0 {
+            return len;
+        }
+        self.chars[self.held - 1].1 += len;
+        0
+    }
+
+    /// Release all held characters
+    ///
+    /// # Arguments
+    /// * boundary: Flag if the stream ends at a word boundary. If set, held characters
+    ///   forming a complete phrase are replaced.
+    ///
+    /// # Notes
+    /// Released characters must be taken via pop, before the next character is passed.
+    pub fn finish(&mut self, boundary: bool) {
+        let dictionary = match self.dictionary {
+            Some(dictionary) => dictionary,
+            None => return,
+        };
+        while self.held > 0 {
+            self.resolve(dictionary, boundary);
+            self.scan(dictionary);
+        }
+    }
+
+    /// Take the next released character
+    ///
+    /// # Returns
+    /// A Some containing the next released character and the number of bytes attributed
+    /// to it, None if there is none.
+    pub fn pop(&mut self) -> Option<(char, usize)> {
+        if self.output_start == self.output_len {
+            self.output_start = 0;
+            self.output_len = 0;
+            return None;
+        }
+        let output = self.output[self.output_start];
+        self.output_start += 1;
+        Some(output)
+    }
+
+    /// Scan the unscanned characters, holding back characters possibly starting a phrase.
+    fn scan(&mut self, dictionary: &Dictionary) {
+        while self.held < self.len {
+            let (char, len) = self.chars[self.held];
+            if self.held == 0 {
+                // Phrases start at word boundaries only.
+                if !self.word && dictionary.is_prefix(&self.chars[..1]) {
+                    self.held = 1;
+                } else {
+                    self.release(char, len);
+                    self.remove(1);
+                }
+                self.word = is_word(char);
+            } else if self.held < MAX_PHRASE_LEN && dictionary.is_prefix(&self.chars[..=self.held])
+            {
+                self.held += 1;
+                self.word = is_word(char);
+            } else {
+                self.resolve(dictionary, !is_word(char));
+            }
+        }
+    }
+
+    /// Release the longest phrase at the start of the held characters, which ends at a word
+    /// boundary, or the first held character. The remaining characters are scanned again.
+    ///
+    /// # Arguments
+    /// * boundary: Flag if the character after the held ones is a word boundary.
+    fn resolve(&mut self, dictionary: &Dictionary, boundary: bool) {
+        let found = (1..=self.held).rev().find_map(|len| {
+            let ends_at_boundary = if len == self.held {
+                boundary
+            } else {
+                !is_word(self.chars[len].0)
+            };
+            if !ends_at_boundary {
+                return None;
+            }
+            dictionary
+                .lookup(&self.chars[..len])
+                .map(|abbreviation| (len, abbreviation))
+        });
+
+        match found {
+            Some((len, abbreviation)) => {
+                let bytes = self.chars[..len].iter().map(|(_, bytes)| bytes).sum();
+                let last = abbreviation.len() - 1;
+                for (index, byte) in abbreviation.iter().enumerate() {
+                    let bytes = if index == last { bytes } else { 0 };
+                    self.release(char::from(*byte), bytes);
+                }
+                self.remove(len);
+            }
+            None => {
+                let (char, len) = self.chars[0];
+                self.release(char, len);
+                self.remove(1);
+            }
+        }
+        // The released characters end within a word.
+        self.held = 0;
+        self.word = true;
+    }
+
+    /// Remove characters from the start of the held and unscanned characters.
+    fn remove(&mut self, count: usize) {
+        self.chars.copy_within(count..self.len, 0);
+        self.len -= count;
+        self.held = self.held.saturating_sub(count);
+    }
+
+    /// Append a character to the released characters.
+    fn release(&mut self, char: char, len: usize) {
+        self.output[self.output_len] = (char, len);
+        self.output_len += 1;
+    }
+}
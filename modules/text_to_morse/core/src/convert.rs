@@ -0,0 +1,129 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+use super::compress::Compressor;
+use super::encoding::BOM;
+use super::morse::{morse_code_from, ERROR_CODE, MAX_MORSE_CODE_LEN};
+use super::normalize::Normalizer;
+use super::utf8::{DecodeError, Policy, Sequence};
+
+/// Reason a conversion stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stop {
+    Full,                 // The morse code of another character might exceed the space
+    End,                  // The input ended
+    Invalid(DecodeError), // The input contains no validly encoded character
+}
+
+/// Result of a conversion.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Conversion {
+    pub bytes_read: usize,    // Number of bytes of all converted characters
+    pub code_len: usize,      // Number of bytes of morse code stored
+    pub chars_converted: u64, // Number of converted characters
+    pub unknown_chars: u64,   // Number of characters converted into the error code
+    pub invalid_utf8: u64,    // Number of replaced or skipped invalid UTF-8 sequences
+    pub stop: Stop,           // Reason the conversion stopped
+}
+
+/// Convert characters into morse code, until the input ends or the space is exhausted
+///
+/// # Arguments
+/// * space: The maximum number of bytes of morse code to store.
+/// * policy: Handling of invalid UTF-8 sequences.
+/// * normalizer: Normalizes each character before its conversion.
+/// * compressor: Replaces phrases of normalized characters by their abbreviations.
+/// * read_char: Source of the sequences to convert. Returns None if the input ended.
+/// * store: Stores the morse code of a converted character.
+///
+/// # Returns
+/// On success, an Ok containing the result of the conversion. Characters are converted as
+/// long as the morse code of any character fits into the remaining space. On failure, an
+/// Err containing the error returned by store. The failed character is not counted.
+///
+/// # Notes
+/// * Invalid sequences are replaced by U+FFFD, which is converted into the error code, or
+///   skipped according to the policy. Policy::Fail stops the conversion instead.
+/// * Truncated sequences always stop the conversion, since more input might complete them.
+/// * Byte order marks and characters dropped by the normalizer are read, but neither
+///   converted nor counted as characters.
+/// * Characters held back by the compressor are released when the conversion stops. A
+///   phrase is only replaced at the end of the input, if it is complete.
+pub fn convert<E>(
+    space: usize,
+    policy: Policy,
+    normalizer: &mut Normalizer,
+    compressor: &mut Compressor,
+    mut read_char: impl FnMut() -> Option<Sequence>,
+    mut store: impl FnMut(char, &'static str) -> Result<(), E>,
+) -> Result<Conversion, E> {
+    let mut conversion = Conversion {
+        bytes_read: 0,
+        code_len: 0,
+        chars_converted: 0,
+        unknown_chars: 0,
+        invalid_utf8: 0,
+        stop: Stop::Full,
+    };
+
+    // Reserve space for the characters held back by the compressor.
+    while conversion.code_len + (compressor.held() + 1) * MAX_MORSE_CODE_LEN <= space {
+        let sequence = match read_char() {
+            Some(sequence) => sequence,
+            None => {
+                conversion.stop = Stop::End;
+                break;
+            }
+        };
+
+        let char = match (sequence.char, policy) {
+            (Ok(char), _) => char,
+            (Err(DecodeError::Invalid), Policy::Replace) => char::REPLACEMENT_CHARACTER,
+            (Err(DecodeError::Invalid), Policy::Skip) => {
+                conversion.bytes_read += compressor.skip(sequence.len);
+                conversion.invalid_utf8 += 1;
+                continue;
+            }
+            (Err(error), _) => {
+                conversion.stop = Stop::Invalid(error);
+                break;
+            }
+        };
+        if sequence.char.is_err() {
+            conversion.invalid_utf8 += 1;
+        }
+        let char = match normalizer.normalize(char) {
+            Some(char) if char != BOM => char,
+            _ => {
+                conversion.bytes_read += compressor.skip(sequence.len);
+                continue;
+            }
+        };
+
+        compressor.push(char, sequence.len);
+        store_released(compressor, &mut conversion, &mut store)?;
+    }
+
+    compressor.finish(conversion.stop == Stop::End);
+    store_released(compressor, &mut conversion, &mut store)?;
+    Ok(conversion)
+}
+
+/// Store the morse code of all characters released by the compressor.
+fn store_released<E>(
+    compressor: &mut Compressor,
+    conversion: &mut Conversion,
+    store: &mut impl FnMut(char, &'static str) -> Result<(), E>,
+) -> Result<(), E> {
+    while let Some((char, len)) = compressor.pop() {
+        let code = morse_code_from(char);
+        store(char, code)?;
+        conversion.bytes_read += len;
+        conversion.code_len += code.len();
+        conversion.chars_converted += 1;
+        if code == ERROR_CODE {
+            conversion.unknown_chars += 1;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,200 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+use super::utf8::{self, DecodeError, Sequence};
+
+/// Byte order mark. It carries no text, so convert skips it in any encoding.
+pub const BOM: char = '\u{feff}';
+
+/// Encoding of written text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,   // UTF-8
+    Latin1, // ISO-8859-1, each byte is a character
+    Utf16,  // UTF-16, byte order given by a BOM, little endian without BOM
+    Ascii,  // ASCII, bytes above 0x7f are invalid
+}
+
+impl Encoding {
+    /// Lookup an encoding by its numeric id
+    ///
+    /// # Arguments
+    /// * id: The numeric id of the encoding, starting at 0.
+    ///
+    /// # Returns
+    /// A Some containing the encoding with the given id, None if there is no such encoding.
+    pub fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(Encoding::Utf8),
+            1 => Some(Encoding::Latin1),
+            2 => Some(Encoding::Utf16),
+            3 => Some(Encoding::Ascii),
+            _ => None,
+        }
+    }
+
+    /// Get the numeric id of an encoding
+    ///
+    /// # Returns
+    /// The numeric id of the encoding. See from_id.
+    pub fn id(self) -> u32 {
+        match self {
+            Encoding::Utf8 => 0,
+            Encoding::Latin1 => 1,
+            Encoding::Utf16 => 2,
+            Encoding::Ascii => 3,
+        }
+    }
+
+    /// Get the name of an encoding
+    ///
+    /// # Returns
+    /// A reference to str containing the common name of the encoding.
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Latin1 => "Latin-1",
+            Encoding::Utf16 => "UTF-16",
+            Encoding::Ascii => "ASCII",
+        }
+    }
+}
+
+/// Byte order of UTF-16 encoded text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteOrder {
+    Little, // Least significant byte first
+    Big,    // Most significant byte first
+}
+
+/// Decoder of characters in a selectable encoding from a stream of bytes.
+pub struct Decoder {
+    encoding: Encoding,            // Encoding of the stream
+    utf8: utf8::Decoder,           // Decoder of UTF-8 streams
+    byte_order: Option<ByteOrder>, // Byte order of UTF-16 streams, None until detected
+    pending: Option<u16>,          // UTF-16 code unit taken from the stream, starting the next
+}
+
+impl Decoder {
+    /// Create a new Decoder
+    ///
+    /// # Arguments
+    /// * encoding: The encoding of the stream.
+    /// * byte_order: The byte order of a UTF-16 stream, if known from previous text.
+    ///   Otherwise it is detected from the first code unit.
+    pub const fn new(encoding: Encoding, byte_order: Option<ByteOrder>) -> Self {
+        Self {
+            encoding,
+            utf8: utf8::Decoder::new(),
+            byte_order,
+            pending: None,
+        }
+    }
+
+    /// Get the byte order of a UTF-16 stream
+    ///
+    /// # Returns
+    /// A Some containing the byte order, None if it was not detected yet.
+    pub fn byte_order(&self) -> Option<ByteOrder> {
+        self.byte_order
+    }
+
+    /// Get the number of bytes taken from the stream, but not decoded yet
+    pub fn pending(&self) -> usize {
+        self.utf8.pending() + self.pending.map_or(0, |_| 2)
+    }
+
+    /// Decode the next character
+    ///
+    /// # Arguments
+    /// * next: Source of the encoded bytes. Returns None if the input ended.
+    ///
+    /// # Returns
+    /// None if the input ended before a new sequence. Otherwise a Some containing the
+    /// sequence, see utf8::Decoder::decode. Bytes above 0x7f are invalid ASCII. Unpaired
+    /// surrogates are invalid UTF-16. Latin-1 sequences are always valid.
+    pub fn decode(&mut self, mut next: impl FnMut() -> Option<u8>) -> Option<Sequence> {
+        match self.encoding {
+            Encoding::Utf8 => self.utf8.decode(next),
+            Encoding::Latin1 => next().map(|byte| Sequence {
+                char: Ok(char::from(byte)),
+                len: 1,
+            }),
+            Encoding::Ascii => next().map(|byte| Sequence {
+                char: if byte.is_ascii() {
+                    Ok(char::from(byte))
+                } else {
+                    Err(DecodeError::Invalid)
+                },
+                len: 1,
+            }),
+            Encoding::Utf16 => self.decode_utf16(next),
+        }
+    }
+
+    /// Decode the next UTF-16 encoded character. See decode.
+    fn decode_utf16(&mut self, mut next: impl FnMut() -> Option<u8>) -> Option<Sequence> {
+        let truncated = |len| Sequence {
+            char: Err(DecodeError::Truncated),
+            len,
+        };
+        let invalid = Sequence {
+            char: Err(DecodeError::Invalid),
+            len: 2,
+        };
+
+        let unit = match self.pending.take() {
+            Some(unit) => unit,
+            None => {
+                let first = next()?;
+                match next() {
+                    Some(second) => self.unit(first, second),
+                    None => return Some(truncated(1)),
+                }
+            }
+        };
+
+        let high = match unit {
+            0xd800..=0xdbff => unit,
+            0xdc00..=0xdfff => return Some(invalid),
+            _ => {
+                // Code units outside of the surrogates are characters.
+                let char = char::from_u32(u32::from(unit)).ok_or(DecodeError::Invalid);
+                return Some(Sequence { char, len: 2 });
+            }
+        };
+
+        let low = match (next(), next()) {
+            (Some(first), Some(second)) => self.unit(first, second),
+            (Some(_), None) => return Some(truncated(3)),
+            _ => return Some(truncated(2)),
+        };
+        if !(0xdc00..=0xdfff).contains(&low) {
+            self.pending = Some(low);
+            return Some(invalid);
+        }
+
+        let code_point = 0x10000 + ((u32::from(high) - 0xd800) << 10) + (u32::from(low) - 0xdc00);
+        let char = char::from_u32(code_point).ok_or(DecodeError::Invalid);
+        Some(Sequence { char, len: 4 })
+    }
+
+    /// Compose a UTF-16 code unit of two bytes, detecting the byte order on the first unit.
+    ///
+    /// # Arguments
+    /// * first: The first byte of the code unit.
+    /// * second: The second byte of the code unit.
+    ///
+    /// # Returns
+    /// The code unit. The byte order is big endian, if the first unit is a big endian BOM.
+    fn unit(&mut self, first: u8, second: u8) -> u16 {
+        let byte_order = *self.byte_order.get_or_insert(match (first, second) {
+            (0xfe, 0xff) => ByteOrder::Big,
+            _ => ByteOrder::Little,
+        });
+        match byte_order {
+            ByteOrder::Little => u16::from_le_bytes([first, second]),
+            ByteOrder::Big => u16::from_be_bytes([first, second]),
+        }
+    }
+}
@@ -0,0 +1,20 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+//! Conversion core of text_to_morse.
+//!
+//! The modules of this crate have no dependencies besides core, so they are shared between
+//! the kernel module and this host crate. The kernel module includes the module files
+//! directly, see main.rs. This crate allows to test and fuzz the conversion core on the
+//! host via `cargo test` and `cargo fuzz`, without building the kernel.
+
+#![no_std]
+
+pub mod compress;
+pub mod convert;
+pub mod encoding;
+pub mod morse;
+pub mod normalize;
+pub mod receive;
+pub mod ringbuffer;
+pub mod utf8;
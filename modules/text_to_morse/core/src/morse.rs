@@ -0,0 +1,200 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+/// Alphabet supported by morse_code_from.
+pub const ALPHABET: &str = "latin";
+
+/// Symbols used in morse code: dit and dah.
+pub const SYMBOLS: &str = ".-";
+
+/// Morse code of the official error sequence. Unknown characters are mapped to it.
+pub const ERROR_CODE: &str = "........ ";
+
+/// Maximum length of morse code returned by morse_code_from.
+pub const MAX_MORSE_CODE_LEN: usize = 9;
+
+/// Maximum length of morse code returned by Prosign::morse_code.
+pub const MAX_PROSIGN_LEN: usize = 7;
+
+/// Characters converted by morse_code_from into a morse code of their own. Lower case
+/// letters and alternative spellings sharing a morse code are left out.
+pub const CHARACTERS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789ÀÄÈÉÖÜßÑ.,:;?!-_()'=+/@\"";
+
+/// Convert a character into its morse code representation
+///
+/// # Arguments
+/// * char: The character (UTF-8) to convert into morse code.
+///
+/// # Returns
+/// A reference to str containing the morse code representation of argument char.
+///
+/// # Note
+/// The values in the lookup table come from https://en.wikipedia.org/wiki/Morse_code.
+/// All whitespaces/control characters are just mapped to the their values
+/// and unknown characters are mapped to ........ (the official error sequence)
+pub fn morse_code_from(char: char) -> &'static str {
+    match char {
+        // Latin letters
+        'A' | 'a' => ".- ",
+        'B' | 'b' => "-... ",
+        'C' | 'c' => "-.-. ",
+        'D' | 'd' => "-.. ",
+        'E' | 'e' => ". ",
+        'F' | 'f' => "..-. ",
+        'G' | 'g' => "--. ",
+        'H' | 'h' => ".... ",
+        'I' | 'i' => ".. ",
+        'J' | 'j' => ".--- ",
+        'K' | 'k' => "-.- ",
+        'L' | 'l' => ".-.. ",
+        'M' | 'm' => "-- ",
+        'N' | 'n' => "-. ",
+        'O' | 'o' => "--- ",
+        'P' | 'p' => ".--. ",
+        'Q' | 'q' => "--.- ",
+        'R' | 'r' => ".-. ",
+        'S' | 's' => "... ",
+        'T' | 't' => "- ",
+        'U' | 'u' => "..- ",
+        'V' | 'v' => "...- ",
+        'W' | 'w' => ".-- ",
+        'X' | 'x' => "-..- ",
+        'Y' | 'y' => "-.-- ",
+        'Z' | 'z' => "--.. ",
+        // Numbers
+        '0' => "----- ",
+        '1' => ".---- ",
+        '2' => "..--- ",
+        '3' => "...-- ",
+        '4' => "....- ",
+        '5' => "..... ",
+        '6' => "-.... ",
+        '7' => "--... ",
+        '8' => "---.. ",
+        '9' => "----. ",
+        // Special characters
+        'À' | 'à' | 'Å' | 'å' => ".--.- ",
+        'Ä' | 'ä' => ".-.- ",
+        'È' | 'è' => ".-..- ",
+        'É' | 'é' => "..-.. ",
+        'Ö' | 'ö' => "---. ",
+        'Ü' | 'ü' => "..-- ",
+        'ß' => "...--.. ",
+        'Ñ' | 'ñ' => "--.-- ",
+        // Punctuation characters
+        '.' => ".-.-.- ",
+        ',' => "--..-- ",
+        ':' => "---... ",
+        ';' => "-.-.-. ",
+        '?' => "..--.. ",
+        '!' => "-.-.-- ",
+        '-' => "-....- ",
+        '_' => "..--.- ",
+        '(' => "-.--. ",
+        ')' => "-.--.- ",
+        '\'' => ".----. ",
+        '=' => "-...- ",
+        '+' => ".-.-. ",
+        '/' => "-..-. ",
+        '@' => ".--.-. ",
+        '"' => ".-..-. ",
+        // Whitespace / control characters
+        ' ' => "   ",
+        '\n' => "\n",
+        '\r' => "\r",
+        '\t' => "\t",
+        '\0' => "\0",
+        // Everything else is mapped to Error
+        _ => ERROR_CODE,
+    }
+}
+
+/// Convert morse code back into a character
+///
+/// # Arguments
+/// * symbols: The symbols of a letter, without the trailing space.
+///
+/// # Returns
+/// A Some containing the upper case character with the given morse code, None if no character
+/// of CHARACTERS has this morse code. The error code yields None.
+///
+/// # Note
+/// The morse code is looked up via morse_code_from, so both directions share a single table.
+pub fn char_from(symbols: &[u8]) -> Option<char> {
+    CHARACTERS.chars().find(|&char| {
+        let code = morse_code_from(char).as_bytes();
+        code.strip_suffix(b" ") == Some(symbols)
+    })
+}
+
+/// Check if a byte of morse code terminates a letter
+///
+/// # Arguments
+/// * byte: A byte of morse code returned by morse_code_from.
+///
+/// # Returns
+/// true if the byte is the last byte of a letter, otherwise false.
+///
+/// # Note
+/// Each morse code ends with a space or is a single whitespace/control character.
+pub fn is_letter_end(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\n' | b'\r' | b'\t' | b'\0')
+}
+
+/// Procedural signals. Each prosign is sent as a single character without letter gaps.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Prosign {
+    AR, // End of message
+    AS, // Wait
+    BT, // Break, separates paragraphs
+    KN, // Invitation to a specific station to transmit
+    SK, // End of contact
+}
+
+impl Prosign {
+    /// Lookup a prosign by its numeric id
+    ///
+    /// # Arguments
+    /// * id: The numeric id of the prosign, starting at 1.
+    ///
+    /// # Returns
+    /// A Some containing the prosign with the given id, None if there is no such prosign.
+    pub fn from_id(id: u32) -> Option<Self> {
+        match id {
+            1 => Some(Prosign::AR),
+            2 => Some(Prosign::AS),
+            3 => Some(Prosign::BT),
+            4 => Some(Prosign::KN),
+            5 => Some(Prosign::SK),
+            _ => None,
+        }
+    }
+
+    /// Get the numeric id of a prosign
+    ///
+    /// # Returns
+    /// The numeric id of the prosign. See from_id.
+    pub fn id(self) -> u32 {
+        match self {
+            Prosign::AR => 1,
+            Prosign::AS => 2,
+            Prosign::BT => 3,
+            Prosign::KN => 4,
+            Prosign::SK => 5,
+        }
+    }
+
+    /// Get the morse code representation of a prosign
+    ///
+    /// # Returns
+    /// A reference to str containing the morse code representation of the prosign.
+    pub fn morse_code(self) -> &'static str {
+        match self {
+            Prosign::AR => ".-.-. ",
+            Prosign::AS => ".-... ",
+            Prosign::BT => "-...- ",
+            Prosign::KN => "-.--. ",
+            Prosign::SK => "...-.- ",
+        }
+    }
+}
@@ -0,0 +1,246 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+/// Character starting a field, if Stages::CUT_FIELDS is switched on. It is dropped.
+pub const FIELD_START: char = '[';
+
+/// Character ending a field, if Stages::CUT_FIELDS is switched on. It is dropped.
+pub const FIELD_END: char = ']';
+
+/// Stages of the text normalization. Each stage is switched on by its flag.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stages(u32);
+
+impl Stages {
+    /// Collapse runs of whitespace other than newlines into a single space.
+    pub const COLLAPSE_WHITESPACE: Self = Self(1 << 0);
+    /// Map typographic quotes, dashes and spaces to their ASCII equivalents.
+    pub const MAP_TYPOGRAPHY: Self = Self(1 << 1);
+    /// Replace digits by their cut numbers, e.g. 0 by T and 9 by N.
+    pub const CUT_NUMBERS: Self = Self(1 << 2);
+    /// Drop control characters other than newlines.
+    pub const STRIP_CONTROLS: Self = Self(1 << 3);
+    /// Replace digits by their cut numbers within fields. See FIELD_START and FIELD_END.
+    pub const CUT_FIELDS: Self = Self(1 << 4);
+    /// Replace phrases by their abbreviations, e.g. "please" by PSE. Applied by a Compressor
+    /// after the other stages, see compress.rs.
+    pub const COMPRESS: Self = Self(1 << 5);
+    /// All stages.
+    pub const ALL: Self = Self(0x3f);
+
+    /// Lookup stages by their flags
+    ///
+    /// # Arguments
+    /// * bits: The flags of the stages. 0 disables the normalization.
+    ///
+    /// # Returns
+    /// A Some containing the stages, None if bits contains unknown flags.
+    pub fn from_bits(bits: u32) -> Option<Self> {
+        if bits & !Self::ALL.0 == 0 {
+            Some(Self(bits))
+        } else {
+            None
+        }
+    }
+
+    /// Get the flags of the stages
+    ///
+    /// # Returns
+    /// The flags of the stages. See from_bits.
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Check if all given stages are switched on
+    ///
+    /// # Arguments
+    /// * other: The stages to check.
+    ///
+    /// # Returns
+    /// true if all stages of other are switched on, otherwise false.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+/// Substitutions of digits by cut numbers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CutNumbers {
+    letters: [u8; 10], // Letter of each digit, or the digit itself if it is not cut
+}
+
+impl CutNumbers {
+    /// Create a cut number table
+    ///
+    /// # Arguments
+    /// * letters: The ASCII letter substituting each digit, starting at 0. A digit is kept,
+    ///   if its entry is the digit itself.
+    ///
+    /// # Returns
+    /// A Some containing the table with upper case letters, None if an entry is neither
+    /// a letter nor its digit or if two digits are substituted by the same letter.
+    pub fn from_letters(mut letters: [u8; 10]) -> Option<Self> {
+        for (digit, letter) in letters.iter_mut().enumerate() {
+            *letter = letter.to_ascii_uppercase();
+            if !letter.is_ascii_uppercase() && *letter != b'0' + digit as u8 {
+                return None;
+            }
+        }
+        for (index, letter) in letters.iter().enumerate() {
+            if letter.is_ascii_uppercase() && letters[index + 1..].contains(letter) {
+                return None;
+            }
+        }
+        Some(Self { letters })
+    }
+
+    /// Get the letters of the table
+    ///
+    /// # Returns
+    /// The letter substituting each digit, starting at 0. See from_letters.
+    pub fn letters(&self) -> [u8; 10] {
+        self.letters
+    }
+
+    /// Replace a digit by its cut number
+    ///
+    /// # Arguments
+    /// * char: The character to replace.
+    ///
+    /// # Returns
+    /// The letter substituting a digit, otherwise char itself.
+    pub fn cut(&self, char: char) -> char {
+        match char.to_digit(10) {
+            Some(digit) => char::from(self.letters[digit as usize]),
+            None => char,
+        }
+    }
+
+    /// Expand a cut number back into its digit
+    ///
+    /// # Arguments
+    /// * char: The character to expand, e.g. decoded from morse code.
+    ///
+    /// # Returns
+    /// The digit substituted by a letter, otherwise char itself. Letters are expanded
+    /// regardless of their case.
+    pub fn expand(&self, char: char) -> char {
+        let letter = char.to_ascii_uppercase();
+        match self
+            .letters
+            .iter()
+            .position(|cut| char::from(*cut) == letter)
+        {
+            Some(digit) => char::from(b'0' + digit as u8),
+            None => char,
+        }
+    }
+}
+
+impl Default for CutNumbers {
+    /// The common cut numbers: 0 T, 1 A, 2 U, 3 V, 5 E, 7 B, 8 D and 9 N. 4 and 6 are kept.
+    fn default() -> Self {
+        Self {
+            letters: *b"TAUV4E6BDN",
+        }
+    }
+}
+
+/// State of a Normalizer, carried over between texts of the same stream.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct State {
+    pub whitespace: bool, // The last character was collapsible whitespace
+    pub field: bool,      // The last character was within a field
+}
+
+/// Normalizer of a stream of characters, applied before their conversion into morse code.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Normalizer {
+    stages: Stages,          // Stages switched on
+    cut_numbers: CutNumbers, // Substitutions of digits
+    state: State,            // State after the last character
+}
+
+impl Normalizer {
+    /// Create a new Normalizer
+    ///
+    /// # Arguments
+    /// * stages: The stages to apply.
+    /// * cut_numbers: The substitutions of digits, if cut numbers are switched on.
+    /// * state: The state after previous text of the stream.
+    pub const fn new(stages: Stages, cut_numbers: CutNumbers, state: State) -> Self {
+        Self {
+            stages,
+            cut_numbers,
+            state,
+        }
+    }
+
+    /// Get the state after the last character
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Normalize the next character
+    ///
+    /// # Arguments
+    /// * char: The character to normalize.
+    ///
+    /// # Returns
+    /// A Some containing the normalized character, None if the character is dropped.
+    ///
+    /// # Notes
+    /// The stages are applied in the order: Fields, typography, whitespace, controls,
+    /// cut numbers. So typographic spaces are collapsed and tabs become spaces instead of
+    /// being dropped.
+    pub fn normalize(&mut self, mut char: char) -> Option<char> {
+        if self.stages.contains(Stages::CUT_FIELDS) && matches!(char, FIELD_START | FIELD_END) {
+            self.state.field = char == FIELD_START;
+            return None;
+        }
+        if self.stages.contains(Stages::MAP_TYPOGRAPHY) {
+            char = typography(char);
+        }
+        if self.stages.contains(Stages::COLLAPSE_WHITESPACE) {
+            let collapsible = char.is_whitespace() && !matches!(char, '\n' | '\r');
+            if collapsible && self.state.whitespace {
+                return None;
+            }
+            self.state.whitespace = collapsible;
+            if collapsible {
+                char = ' ';
+            }
+        }
+        if self.stages.contains(Stages::STRIP_CONTROLS) && char.is_control() && char != '\n' {
+            return None;
+        }
+        let field = self.state.field && self.stages.contains(Stages::CUT_FIELDS);
+        if field || self.stages.contains(Stages::CUT_NUMBERS) {
+            char = self.cut_numbers.cut(char);
+        }
+        Some(char)
+    }
+}
+
+/// Map typographic characters to their ASCII equivalents
+///
+/// # Arguments
+/// * char: The character to map.
+///
+/// # Returns
+/// The ASCII equivalent of a typographic quote, dash or space, otherwise char itself.
+fn typography(char: char) -> char {
+    match char {
+        // Single quotes and primes
+        '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{201b}' | '\u{2032}' => '\'',
+        // Double quotes, guillemets and double primes
+        '\u{201c}' | '\u{201d}' | '\u{201e}' | '\u{201f}' | '\u{2033}' | '«' | '»' => '"',
+        // Hyphens, dashes and the minus sign
+        '\u{2010}'..='\u{2015}' | '\u{2212}' => '-',
+        // No-break and fixed width spaces
+        '\u{a0}' | '\u{2000}'..='\u{200a}' | '\u{202f}' | '\u{205f}' => ' ',
+        // Fraction slash
+        '\u{2044}' => '/',
+        _ => char,
+    }
+}
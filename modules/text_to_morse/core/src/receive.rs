@@ -0,0 +1,142 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+use super::{morse::char_from, normalize::CutNumbers};
+
+/// Maximum number of symbols of a letter. Longer letters are unknown.
+pub const MAX_SYMBOLS: usize = 8;
+
+/// Number of spaces after a letter gap, which form a word gap. See morse_code_from(' ').
+pub const WORD_GAP_LEN: usize = 3;
+
+/// Receiver converting morse code back into text, byte by byte.
+///
+/// The morse code is expected in the format of morse_code_from: The symbols of a letter
+/// end with a space, a space within text becomes three further spaces. Newlines, carriage
+/// returns, tabs and NUL are kept. Any other byte is ignored.
+///
+/// Cut numbers are only expanded back into digits, if enabled via set_expansion. Morse code
+/// doesn't mark where cut numbers are, so every letter substituting a digit is expanded,
+/// e.g. "TEN" becomes "059". The expansion suits exchanges of numbers, like in contests.
+pub struct Receiver {
+    symbols: [u8; MAX_SYMBOLS],    // Symbols of the current letter
+    len: usize,                    // Number of symbols of the current letter
+    overlong: bool,                // Flag if the current letter has too many symbols
+    gap: usize,                    // Number of spaces since the last letter gap
+    expansion: Option<CutNumbers>, // Cut numbers expanded into digits, if enabled
+}
+
+impl Default for Receiver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Receiver {
+    /// Create a Receiver expecting the start of a letter
+    ///
+    /// # Returns
+    /// A new Receiver
+    pub const fn new() -> Self {
+        Self {
+            symbols: [0; MAX_SYMBOLS],
+            len: 0,
+            overlong: false,
+            gap: 0,
+            expansion: None,
+        }
+    }
+
+    /// Set the cut numbers to expand into digits
+    ///
+    /// # Arguments
+    /// * expansion: The cut numbers to expand, None to keep letters as received.
+    pub fn set_expansion(&mut self, expansion: Option<CutNumbers>) {
+        self.expansion = expansion;
+    }
+
+    /// Get the cut numbers expanded into digits
+    ///
+    /// # Returns
+    /// A Some containing the expanded cut numbers, None if letters are kept as received.
+    pub fn expansion(&self) -> Option<CutNumbers> {
+        self.expansion
+    }
+
+    /// Drop the symbols of an unfinished letter and the spaces received since the last
+    /// letter gap. The expansion is kept.
+    pub fn reset(&mut self) {
+        *self = Self {
+            expansion: self.expansion,
+            ..Self::new()
+        };
+    }
+
+    /// Check if the Receiver holds symbols of an unfinished letter
+    ///
+    /// # Returns
+    /// true if symbols were received since the last letter gap, otherwise false.
+    pub fn in_letter(&self) -> bool {
+        self.len != 0 || self.overlong
+    }
+
+    /// Receive a byte of morse code
+    ///
+    /// # Arguments
+    /// * byte: The received byte.
+    /// * store: Function storing a received character. Called at most twice.
+    ///
+    /// # Note
+    /// Letters are stored in upper case, or as their digit if they are expanded cut numbers.
+    /// Unknown letters, including the error code, are stored as U+FFFD. A letter not finished by a space is finished by a following
+    /// newline, carriage return, tab or NUL.
+    pub fn push(&mut self, byte: u8, mut store: impl FnMut(char)) {
+        match byte {
+            b'.' | b'-' => {
+                match self.symbols.get_mut(self.len) {
+                    Some(symbol) => {
+                        *symbol = byte;
+                        self.len += 1;
+                    }
+                    None => self.overlong = true,
+                }
+                self.gap = 0;
+            }
+            b' ' if self.in_letter() => store(self.finish_letter()),
+            b' ' => {
+                self.gap += 1;
+                if self.gap == WORD_GAP_LEN {
+                    self.gap = 0;
+                    store(' ');
+                }
+            }
+            b'\n' | b'\r' | b'\t' | b'\0' => {
+                if self.in_letter() {
+                    store(self.finish_letter());
+                }
+                self.gap = 0;
+                store(byte as char);
+            }
+            _ => (),
+        }
+    }
+
+    /// Finish the current letter
+    ///
+    /// # Returns
+    /// The character of the letter, U+FFFD if it is unknown.
+    fn finish_letter(&mut self) -> char {
+        let char = match self.overlong {
+            true => None,
+            false => char_from(&self.symbols[..self.len]),
+        };
+        self.len = 0;
+        self.overlong = false;
+        self.gap = 0;
+        match (char, self.expansion) {
+            (Some(char), Some(expansion)) => expansion.expand(char),
+            (Some(char), None) => char,
+            (None, _) => char::REPLACEMENT_CHARACTER,
+        }
+    }
+}
@@ -0,0 +1,315 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+use core::{marker::PhantomData, mem::MaybeUninit};
+
+/// Errors of Ringbuffer operations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    Full,  // The Ringbuffer has no free slot left
+    Empty, // The Ringbuffer contains no value
+}
+
+/// Storage of a Ringbuffer, e.g. an array or a Vec of MaybeUninit.
+pub trait Slots<T>: AsRef<[MaybeUninit<T>]> + AsMut<[MaybeUninit<T>]> {}
+
+impl<T, S: AsRef<[MaybeUninit<T>]> + AsMut<[MaybeUninit<T>]>> Slots<T> for S {}
+
+/// Simple, generic Ringbuffer with FIFO semantics, storing its values in the slots S.
+pub struct Ring<T, S> {
+    buffer: S,               // Slots used to store objects
+    rpos: usize,             // Current read position
+    wpos: usize,             // Current write position
+    used: usize,             // Number of used slots in buffer
+    _values: PhantomData<T>, // Type of the stored objects
+}
+
+/// Array backed Ringbuffer, storing up to N values.
+pub type Ringbuffer<T, const N: usize> = Ring<T, [MaybeUninit<T>; N]>;
+
+impl<T: Copy, const N: usize> Default for Ringbuffer<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Copy, const N: usize> Ringbuffer<T, N> {
+    /// Create a empty Ringbuffer
+    ///
+    /// # Returns
+    /// An empty Ringbuffer
+    pub fn new() -> Self {
+        Self::with_slots([MaybeUninit::uninit(); N])
+    }
+}
+
+impl<T: Copy, S: Slots<T>> Ring<T, S> {
+    /// Create a empty Ringbuffer using the given slots, e.g. a heap allocated Vec.
+    ///
+    /// # Arguments
+    /// * slots: The slots to store values in. Their number is the size of the Ringbuffer.
+    ///
+    /// # Returns
+    /// An empty Ringbuffer
+    pub fn with_slots(slots: S) -> Self {
+        Self {
+            buffer: slots,
+            rpos: 0,
+            wpos: 0,
+            used: 0,
+            _values: PhantomData,
+        }
+    }
+
+    /// Try to append a value in the Ringbuffer.
+    ///
+    /// # Arguments
+    /// * val: The value to store.
+    ///
+    /// # Returns
+    /// In case the Ringbuffer is full, an Err containing Error::Full is returned, otherwise
+    /// an Ok containing the unit value is returned.
+    pub fn try_push(&mut self, val: T) -> Result<(), Error> {
+        if self.used >= self.size() {
+            return Err(Error::Full);
+        }
+
+        self.buffer.as_mut()[self.wpos] = MaybeUninit::new(val);
+        self.used += 1;
+        self.wpos += 1;
+
+        if self.wpos >= self.size() {
+            self.wpos = 0;
+        }
+        Ok(())
+    }
+
+    /// Try to get a value from the Ringbuffer.
+    ///
+    /// # Returns
+    /// In case the Ringbuffer is empty, an Err containing Error::Empty is returned, otherwise
+    /// an Ok the oldest value in the Ringbuffer.
+    pub fn try_pop(&mut self) -> Result<T, Error> {
+        if self.used == 0 {
+            return Err(Error::Empty);
+        }
+
+        // SAFETY: The used slots starting at rpos were initialized by try_push.
+        let val = unsafe { self.buffer.as_ref()[self.rpos].assume_init() };
+
+        self.used -= 1;
+        self.rpos += 1;
+
+        if self.rpos >= self.size() {
+            self.rpos = 0
+        }
+        Ok(val)
+    }
+
+    /// Append a value in the Ringbuffer. If the Ringbuffer is full, the oldest value is evicted.
+    ///
+    /// # Arguments
+    /// * val: The value to store.
+    ///
+    /// # Returns
+    /// In case the Ringbuffer was full, a Some containing the evicted value is returned,
+    /// otherwise None.
+    pub fn push_overwrite(&mut self, val: T) -> Option<T> {
+        let evicted = if self.is_full() {
+            self.try_pop().ok()
+        } else {
+            None
+        };
+
+        self.try_push(val).unwrap(); // There is at least one free slot, it should never fail.
+        evicted
+    }
+
+    /// Remove values from the Ringbuffer until a value marking a boundary was removed.
+    ///
+    /// # Arguments
+    /// * is_boundary: Predicate returning true for values marking a boundary.
+    ///
+    /// # Returns
+    /// The number of removed values, including the boundary value.
+    ///
+    /// # Note
+    /// If the Ringbuffer contains no boundary value, the Ringbuffer is emptied.
+    pub fn pop_until(&mut self, is_boundary: impl Fn(&T) -> bool) -> usize {
+        let mut removed = 0usize;
+        while let Ok(val) = self.try_pop() {
+            removed += 1;
+            if is_boundary(&val) {
+                break;
+            }
+        }
+        removed
+    }
+
+    /// Get a reference to the oldest value in the Ringbuffer without removing it.
+    ///
+    /// # Returns
+    /// In case the Ringbuffer is empty, None is returned, otherwise a Some containing
+    /// a reference to the oldest value in the Ringbuffer.
+    pub fn peek(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    /// Get a reference to a value in the Ringbuffer without removing it.
+    ///
+    /// # Arguments
+    /// * index: The position of the value, relative to the oldest value in the Ringbuffer.
+    ///
+    /// # Returns
+    /// In case index is out of bounds, None is returned, otherwise a Some containing
+    /// a reference to the value at the given position.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.used {
+            return None;
+        }
+        // SAFETY: index is within the used slots, which were initialized by try_push.
+        let slot = &self.buffer.as_ref()[(self.rpos + index) % self.size()];
+        Some(unsafe { slot.assume_init_ref() })
+    }
+
+    /// Get an iterator over all values in the Ringbuffer, from oldest to newest.
+    ///
+    /// # Returns
+    /// An iterator yielding references to all stored values without removing them.
+    pub fn iter(&self) -> Iter<'_, T, S> {
+        Iter {
+            ringbuffer: self,
+            index: 0,
+        }
+    }
+
+    /// Remove all values from the Ringbuffer and get an iterator over them.
+    ///
+    /// # Returns
+    /// An iterator yielding all removed values, from oldest to newest.
+    ///
+    /// # Note
+    /// All values not consumed by the iterator are removed when the iterator is dropped.
+    pub fn drain(&mut self) -> Drain<'_, T, S> {
+        Drain { ringbuffer: self }
+    }
+
+    /// Remove all values from the Ringbuffer.
+    pub fn clear(&mut self) {
+        self.rpos = 0;
+        self.wpos = 0;
+        self.used = 0;
+    }
+
+    /// Get the number of currently stored objects in the Ringbuffer.
+    ///
+    /// # Returns
+    /// The number of currently stored objects.
+    pub fn len(&self) -> usize {
+        self.used
+    }
+
+    /// Get the current read position in the backing array.
+    ///
+    /// # Returns
+    /// The index of the oldest stored object, if the Ringbuffer is not empty.
+    pub fn read_pos(&self) -> usize {
+        self.rpos
+    }
+
+    /// Get the current write position in the backing array.
+    ///
+    /// # Returns
+    /// The index the next object is stored at.
+    pub fn write_pos(&self) -> usize {
+        self.wpos
+    }
+
+    /// Get the number of object that could be stored until the Ringbuffer is full.
+    ///
+    /// # Returns
+    /// The number of free slots.
+    pub fn free(&self) -> usize {
+        self.size() - self.used
+    }
+
+    /// Get the total number of objects that can be stored in the Ringbuffer.
+    ///
+    /// # Returns
+    /// The number of total slots.
+    ///
+    /// # Note
+    /// This is equivalent to the number of slots given on creation.
+    pub fn size(&self) -> usize {
+        self.buffer.as_ref().len()
+    }
+
+    /// Check if the Ringbuffer is empty.
+    ///
+    /// # Returns
+    /// true if the entire Ringbuffer is empty, otherwise false.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Check if the Ringbuffer is full.
+    ///
+    /// # Returns
+    /// true if the entire Ringbuffer is full, otherwise false.
+    pub fn is_full(&self) -> bool {
+        self.free() == 0
+    }
+}
+
+/// Iterator over references to all values stored in a Ringbuffer.
+pub struct Iter<'a, T, S> {
+    ringbuffer: &'a Ring<T, S>, // Ringbuffer to iterate over
+    index: usize,               // Position of the next value, relative to the oldest one
+}
+
+impl<'a, T: Copy, S: Slots<T>> Iterator for Iter<'a, T, S> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let val = self.ringbuffer.get(self.index)?;
+        self.index += 1;
+        Some(val)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.index = self.index.saturating_add(n);
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.ringbuffer.len().saturating_sub(self.index);
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a, T: Copy, S: Slots<T>> ExactSizeIterator for Iter<'a, T, S> {}
+
+/// Iterator removing all values from a Ringbuffer.
+pub struct Drain<'a, T: Copy, S: Slots<T>> {
+    ringbuffer: &'a mut Ring<T, S>, // Ringbuffer to remove values from
+}
+
+impl<'a, T: Copy, S: Slots<T>> Iterator for Drain<'a, T, S> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.ringbuffer.try_pop().ok()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.ringbuffer.len(), Some(self.ringbuffer.len()))
+    }
+}
+
+impl<'a, T: Copy, S: Slots<T>> ExactSizeIterator for Drain<'a, T, S> {}
+
+impl<'a, T: Copy, S: Slots<T>> Drop for Drain<'a, T, S> {
+    fn drop(&mut self) {
+        self.ringbuffer.clear();
+    }
+}
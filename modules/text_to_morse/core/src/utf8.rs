@@ -0,0 +1,186 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+/// Errors of Decoder::decode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    Truncated, // The input ended before the character was complete
+    Invalid,   // The input contains no valid UTF-8 encoded character
+}
+
+/// Get the length of a UTF-8 encoded character
+///
+/// # Arguments
+/// * byte: The first byte of the encoded character.
+///
+/// # Returns
+/// A Some containing the number of bytes of the encoded character, including the given byte.
+/// None if the byte can't start a UTF-8 encoded character.
+pub fn sequence_len(byte: u8) -> Option<usize> {
+    // Bitfiddling constants to determine byte length of expected char.
+    const MASK_1BYTE: u8 = 0b10000000;
+    const BITS_1BYTE: u8 = 0b00000000;
+    const MASK_2BYTE: u8 = 0b11100000;
+    const BITS_2BYTE: u8 = 0b11000000;
+    const MASK_3BYTE: u8 = 0b11110000;
+    const BITS_3BYTE: u8 = 0b11100000;
+    const MASK_4BYTE: u8 = 0b11111000;
+    const BITS_4BYTE: u8 = 0b11110000;
+
+    if (byte & MASK_1BYTE) == BITS_1BYTE {
+        Some(1)
+    } else if (byte & MASK_2BYTE) == BITS_2BYTE {
+        Some(2)
+    } else if (byte & MASK_3BYTE) == BITS_3BYTE {
+        Some(3)
+    } else if (byte & MASK_4BYTE) == BITS_4BYTE {
+        Some(4)
+    } else {
+        None
+    }
+}
+
+/// Handling of invalid UTF-8 sequences.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Policy {
+    Fail,    // Stop at the invalid sequence
+    Replace, // Replace the invalid sequence with U+FFFD
+    Skip,    // Skip the invalid sequence
+}
+
+impl Policy {
+    /// Lookup a policy by its numeric id
+    ///
+    /// # Arguments
+    /// * id: The numeric id of the policy, starting at 0.
+    ///
+    /// # Returns
+    /// A Some containing the policy with the given id, None if there is no such policy.
+    pub fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(Policy::Fail),
+            1 => Some(Policy::Replace),
+            2 => Some(Policy::Skip),
+            _ => None,
+        }
+    }
+
+    /// Get the numeric id of a policy
+    ///
+    /// # Returns
+    /// The numeric id of the policy. See from_id.
+    pub fn id(self) -> u32 {
+        match self {
+            Policy::Fail => 0,
+            Policy::Replace => 1,
+            Policy::Skip => 2,
+        }
+    }
+}
+
+/// A single UTF-8 sequence, read by Decoder::decode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sequence {
+    pub char: Result<char, DecodeError>, // The decoded character or the reason decoding failed
+    pub len: usize,                      // Number of bytes of the sequence
+}
+
+/// Decoder of UTF-8 encoded characters from a stream of bytes.
+///
+/// An invalid sequence ends before the first byte that can't continue it. This is the
+/// maximal subpart of Unicode chapter 3.9, so a valid character following an invalid
+/// sequence is never swallowed. The decoder keeps the byte and starts the next sequence
+/// with it, since a stream can't give it back.
+#[derive(Default)]
+pub struct Decoder {
+    pending: Option<u8>, // Byte taken from the stream, starting the next sequence
+}
+
+impl Decoder {
+    /// Create a new Decoder
+    pub const fn new() -> Self {
+        Self { pending: None }
+    }
+
+    /// Get the number of bytes taken from the stream, but not decoded yet
+    ///
+    /// # Returns
+    /// 1 if a byte starting the next sequence is kept, otherwise 0.
+    pub fn pending(&self) -> usize {
+        self.pending.map_or(0, |_| 1)
+    }
+
+    /// Decode the next UTF-8 sequence
+    ///
+    /// # Arguments
+    /// * next: Source of the encoded bytes. Returns None if the input ended.
+    ///
+    /// # Returns
+    /// None if the input ended before a new sequence. Otherwise a Some containing the
+    /// sequence. Only the bytes of this sequence are taken from next, apart from the byte
+    /// ending an invalid sequence. Its char contains either an Ok with the decoded character
+    /// or an Err containing one of the following errors:
+    /// * DecodeError::Truncated: next ended before the character was complete. More bytes
+    ///   might complete it.
+    /// * DecodeError::Invalid: The bytes are no valid UTF-8, e.g. overlong encodings,
+    ///   surrogates or code points above U+10FFFF.
+    pub fn decode(&mut self, mut next: impl FnMut() -> Option<u8>) -> Option<Sequence> {
+        let first = self.pending.take().or_else(&mut next)?;
+        let invalid = |len| Sequence {
+            char: Err(DecodeError::Invalid),
+            len,
+        };
+
+        // Range of the second byte, excluding overlong encodings, surrogates and
+        // code points above U+10FFFF. All further bytes are in range 0x80..=0xbf.
+        let (len, second) = match first {
+            0x00..=0x7f => (1, 0x80..=0xbf),
+            0xc2..=0xdf => (2, 0x80..=0xbf),
+            0xe0 => (3, 0xa0..=0xbf),
+            0xe1..=0xec | 0xee..=0xef => (3, 0x80..=0xbf),
+            0xed => (3, 0x80..=0x9f),
+            0xf0 => (4, 0x90..=0xbf),
+            0xf1..=0xf3 => (4, 0x80..=0xbf),
+            0xf4 => (4, 0x80..=0x8f),
+            _ => return Some(invalid(1)),
+        };
+
+        let mut code_point = match len {
+            1 => u32::from(first),
+            2 => u32::from(first & 0x1f),
+            3 => u32::from(first & 0x0f),
+            _ => u32::from(first & 0x07),
+        };
+        for index in 1..len {
+            let byte = match next() {
+                Some(byte) => byte,
+                None => {
+                    return Some(Sequence {
+                        char: Err(DecodeError::Truncated),
+                        len: index,
+                    })
+                }
+            };
+
+            let range = if index == 1 {
+                second.clone()
+            } else {
+                0x80..=0xbf
+            };
+            if !range.contains(&byte) {
+                self.pending = Some(byte);
+                return Some(invalid(index));
+            }
+            code_point = (code_point << 6) | u32::from(byte & 0x3f);
+        }
+
+        // The checks above only leave valid code points.
+        Some(match char::from_u32(code_point) {
+            Some(char) => Sequence {
+                char: Ok(char),
+                len,
+            },
+            None => invalid(len),
+        })
+    }
+}
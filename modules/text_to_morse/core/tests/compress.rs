@@ -0,0 +1,157 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+use text_to_morse_core::compress::{
+    Compressor, Dictionary, DictionaryError, DEFAULT_DICTIONARY, MAX_ENTRIES,
+};
+
+/// Compress a text with the given dictionary, attributing a byte to each character.
+fn compress_with(text: &str, dictionary: &Dictionary) -> (String, usize) {
+    let mut compressor = Compressor::new(Some(dictionary), false);
+    let mut output = Vec::new();
+    for char in text.chars() {
+        compressor.push(char, 1);
+        output.extend(std::iter::from_fn(|| compressor.pop()));
+    }
+    compressor.finish(true);
+    output.extend(std::iter::from_fn(|| compressor.pop()));
+    let bytes = output.iter().map(|(_, len)| len).sum();
+    (output.iter().map(|(char, _)| char).collect(), bytes)
+}
+
+/// Compress a text with the default dictionary.
+fn compress(text: &str) -> String {
+    let (compressed, bytes) = compress_with(text, &DEFAULT_DICTIONARY);
+    assert_eq!(bytes, text.chars().count());
+    compressed
+}
+
+#[test]
+fn replaces_phrases() {
+    assert_eq!(compress("thanks, best regards"), "TNX, 73");
+    assert_eq!(compress("Please send WEATHER report"), "PSE send WX RPT");
+    assert_eq!(compress("my location is Berlin"), "QTH Berlin");
+}
+
+#[test]
+fn replaces_q_codes() {
+    assert_eq!(compress("QRZ? who is calling me?"), "QRZ? QRZ?");
+    assert_eq!(compress("i am busy, i am ready"), "QRL, QRV");
+    assert_eq!(compress("interference and static"), "QRM and QRN");
+    assert_eq!(compress("send slower, stop sending"), "QRS, QRT");
+    assert_eq!(compress("I acknowledge receipt"), "QSL");
+    assert_eq!(compress("change frequency to 7030"), "QSY to 7030");
+}
+
+#[test]
+fn replaces_at_word_boundaries_only() {
+    assert_eq!(compress("thanksgiving"), "thanksgiving");
+    assert_eq!(compress("nothanks"), "nothanks");
+    assert_eq!(compress("best regardless"), "best regardless");
+    assert_eq!(compress("best"), "best");
+    assert_eq!(compress("(thanks)"), "(TNX)");
+}
+
+#[test]
+fn prefers_longest_phrase() {
+    assert_eq!(compress("thank you"), "TU");
+    assert_eq!(compress("thank yo"), "thank yo");
+    assert_eq!(compress("my my location is"), "my QTH");
+}
+
+#[test]
+fn disabled() {
+    let mut compressor = Compressor::new(None, false);
+    compressor.push('t', 1);
+    assert_eq!(compressor.pop(), Some(('t', 1)));
+    assert_eq!(compressor.pop(), None);
+    assert_eq!(compressor.held(), 0);
+}
+
+#[test]
+fn attributes_bytes_to_abbreviation() {
+    let mut compressor = Compressor::new(Some(&DEFAULT_DICTIONARY), false);
+    for char in "please".chars() {
+        compressor.push(char, 1);
+    }
+    assert_eq!(compressor.held(), 6);
+    assert_eq!(compressor.skip(3), 0);
+    assert_eq!(compressor.pop(), None);
+
+    compressor.push(' ', 1);
+    let output: Vec<_> = std::iter::from_fn(|| compressor.pop()).collect();
+    assert_eq!(output, [('P', 0), ('S', 0), ('E', 9), (' ', 1)]);
+    assert_eq!(compressor.skip(2), 2);
+}
+
+#[test]
+fn finish_without_boundary_keeps_phrase() {
+    let mut compressor = Compressor::new(Some(&DEFAULT_DICTIONARY), false);
+    for char in "thanks".chars() {
+        compressor.push(char, 1);
+    }
+    compressor.finish(false);
+    let output: String = std::iter::from_fn(|| compressor.pop())
+        .map(|(char, _)| char)
+        .collect();
+    assert_eq!(output, "thanks");
+    assert!(compressor.word());
+
+    // Within a word, no phrase starts.
+    let mut compressor = Compressor::new(Some(&DEFAULT_DICTIONARY), true);
+    compressor.push('t', 1);
+    assert_eq!(compressor.held(), 0);
+}
+
+#[test]
+fn dictionary_entries() {
+    let mut dictionary = Dictionary::new();
+    assert!(dictionary.is_empty());
+    assert_eq!(dictionary.try_add(b"QSL Card", b"qsl"), Ok(()));
+    assert_eq!(compress_with("send qsl card", &dictionary).0, "send QSL");
+
+    // An entry with the same phrase is replaced.
+    assert_eq!(dictionary.try_add(b"qsl card", b"QSLC"), Ok(()));
+    assert_eq!(dictionary.len(), 1);
+    let entries: Vec<_> = dictionary.iter().collect();
+    assert_eq!(entries, [(&b"qsl card"[..], &b"QSLC"[..])]);
+
+    // A clone copies all entries into an existing dictionary.
+    let mut clone = Dictionary::new();
+    clone.clone_from(&DEFAULT_DICTIONARY);
+    assert!(clone.iter().eq(DEFAULT_DICTIONARY.iter()));
+    assert!(dictionary.clone().iter().eq(dictionary.iter()));
+
+    dictionary.clear();
+    assert!(dictionary.is_empty());
+    assert_eq!(Dictionary::default().len(), DEFAULT_DICTIONARY.len());
+}
+
+#[test]
+fn dictionary_rejects_invalid_entries() {
+    let mut dictionary = Dictionary::new();
+    let invalid: [(&[u8], &[u8]); 7] = [
+        (b"", b"A"),
+        (b"a  b", b"A"),
+        (b" ab", b"A"),
+        (b"ab!", b"A"),
+        (b"ab", b""),
+        (b"ab", b"ABC"),
+        (b"a very long phrase of words", b"AVLP"),
+    ];
+    for (phrase, abbreviation) in invalid {
+        assert_eq!(
+            dictionary.try_add(phrase, abbreviation),
+            Err(DictionaryError::Invalid)
+        );
+    }
+
+    for index in 0..MAX_ENTRIES {
+        let phrase = format!("phrase {index}");
+        assert_eq!(dictionary.try_add(phrase.as_bytes(), b"P"), Ok(()));
+    }
+    assert_eq!(
+        dictionary.try_add(b"full", b"F"),
+        Err(DictionaryError::Full)
+    );
+}
@@ -0,0 +1,183 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+use std::convert::Infallible;
+use text_to_morse_core::{
+    compress::{Compressor, DEFAULT_DICTIONARY},
+    convert::{convert, Conversion, Stop},
+    morse::ERROR_CODE,
+    morse::MAX_MORSE_CODE_LEN,
+    normalize::{CutNumbers, Normalizer, Stages, State},
+    utf8::{DecodeError, Decoder, Policy, Sequence},
+};
+
+/// Convert a byte sequence into morse code.
+fn convert_with(bytes: &[u8], space: usize, policy: Policy) -> (Conversion, String) {
+    let mut bytes = bytes.iter().copied();
+    let mut decoder = Decoder::new();
+    let mut morse_code = String::new();
+    let read_char = || decoder.decode(|| bytes.next());
+    let store = |_, code: &str| {
+        morse_code.push_str(code);
+        Ok::<_, Infallible>(())
+    };
+    let normalizer = &mut Normalizer::default();
+    let compressor = &mut Compressor::new(None, false);
+    let conversion = convert(space, policy, normalizer, compressor, read_char, store).unwrap();
+    (conversion, morse_code)
+}
+
+/// Convert a byte sequence into morse code, failing on invalid UTF-8.
+fn convert_bytes(bytes: &[u8], space: usize) -> (Conversion, String) {
+    convert_with(bytes, space, Policy::Fail)
+}
+
+#[test]
+fn converts_until_end() {
+    let (conversion, morse_code) = convert_bytes("SOS ä#".as_bytes(), 256);
+    assert_eq!(morse_code, "... --- ...    .-.- ........ ");
+    assert_eq!(conversion.bytes_read, 7);
+    assert_eq!(conversion.code_len, morse_code.len());
+    assert_eq!(conversion.chars_converted, 6);
+    assert_eq!(conversion.unknown_chars, 1);
+    assert_eq!(conversion.stop, Stop::End);
+}
+
+#[test]
+fn stops_on_invalid_utf8() {
+    let (conversion, morse_code) = convert_bytes(b"ab\xffcd", 256);
+    assert_eq!(morse_code, ".- -... ");
+    assert_eq!(conversion.bytes_read, 2);
+    assert_eq!(conversion.stop, Stop::Invalid(DecodeError::Invalid));
+
+    let (conversion, _) = convert_bytes(b"ab\xe2\x82", 256);
+    assert_eq!(conversion.bytes_read, 2);
+    assert_eq!(conversion.stop, Stop::Invalid(DecodeError::Truncated));
+}
+
+#[test]
+fn replaces_invalid_utf8() {
+    let (conversion, morse_code) = convert_with(b"a\xff\xe2\x82b", 256, Policy::Replace);
+    assert_eq!(
+        morse_code,
+        [".- ", ERROR_CODE, ERROR_CODE, "-... "].concat()
+    );
+    assert_eq!(conversion.bytes_read, 5);
+    assert_eq!(conversion.chars_converted, 4);
+    assert_eq!(conversion.unknown_chars, 2);
+    assert_eq!(conversion.invalid_utf8, 2);
+    assert_eq!(conversion.stop, Stop::End);
+}
+
+#[test]
+fn skips_invalid_utf8() {
+    let (conversion, morse_code) = convert_with(b"a\xff\xe2\x82b", 256, Policy::Skip);
+    assert_eq!(morse_code, ".- -... ");
+    assert_eq!(conversion.bytes_read, 5);
+    assert_eq!(conversion.chars_converted, 2);
+    assert_eq!(conversion.invalid_utf8, 2);
+    assert_eq!(conversion.stop, Stop::End);
+}
+
+#[test]
+fn stops_on_truncated_utf8_regardless_of_policy() {
+    for policy in [Policy::Fail, Policy::Replace, Policy::Skip] {
+        let (conversion, morse_code) = convert_with(b"ab\xe2\x82", 256, policy);
+        assert_eq!(morse_code, ".- -... ");
+        assert_eq!(conversion.bytes_read, 2);
+        assert_eq!(conversion.invalid_utf8, 0);
+        assert_eq!(conversion.stop, Stop::Invalid(DecodeError::Truncated));
+    }
+}
+
+#[test]
+fn skips_byte_order_marks() {
+    let (conversion, morse_code) = convert_bytes("\u{feff}E\u{feff}".as_bytes(), 256);
+    assert_eq!(morse_code, ". ");
+    assert_eq!(conversion.bytes_read, 7);
+    assert_eq!(conversion.chars_converted, 1);
+    assert_eq!(conversion.stop, Stop::End);
+}
+
+#[test]
+fn normalizes_before_conversion() {
+    let mut bytes = "0  \u{a0}9".bytes();
+    let mut decoder = Decoder::new();
+    let mut morse_code = String::new();
+    let read_char = || decoder.decode(|| bytes.next());
+    let store = |_, code: &str| {
+        morse_code.push_str(code);
+        Ok::<_, Infallible>(())
+    };
+    let normalizer = &mut Normalizer::new(Stages::ALL, CutNumbers::default(), State::default());
+    let compressor = &mut Compressor::new(None, false);
+    let conversion = convert(256, Policy::Fail, normalizer, compressor, read_char, store).unwrap();
+    assert_eq!(morse_code, "-    -. ");
+    assert_eq!(conversion.bytes_read, 6);
+    assert_eq!(conversion.chars_converted, 3);
+    assert_eq!(conversion.stop, Stop::End);
+}
+
+#[test]
+fn stops_before_space_is_exceeded() {
+    let input = "E".repeat(100);
+    let (conversion, morse_code) = convert_bytes(input.as_bytes(), 2 * MAX_MORSE_CODE_LEN);
+    assert_eq!(conversion.stop, Stop::Full);
+    assert_eq!(
+        conversion.chars_converted,
+        MAX_MORSE_CODE_LEN as u64 / 2 + 1
+    );
+    assert!(morse_code.len() + MAX_MORSE_CODE_LEN > 2 * MAX_MORSE_CODE_LEN);
+    assert!(morse_code.len() <= 2 * MAX_MORSE_CODE_LEN);
+
+    let (conversion, morse_code) = convert_bytes(b"E", MAX_MORSE_CODE_LEN - 1);
+    assert_eq!(conversion.stop, Stop::Full);
+    assert!(morse_code.is_empty());
+}
+
+#[test]
+fn store_errors_are_returned() {
+    let mut chars = "abc".chars();
+    let mut stored = 0;
+    let store = |_, _: &str| {
+        stored += 1;
+        if stored == 2 {
+            Err("full")
+        } else {
+            Ok(())
+        }
+    };
+    let read_char = || {
+        chars.next().map(|char| Sequence {
+            char: Ok(char),
+            len: char.len_utf8(),
+        })
+    };
+    let normalizer = &mut Normalizer::default();
+    let compressor = &mut Compressor::new(None, false);
+    assert_eq!(
+        convert(256, Policy::Fail, normalizer, compressor, read_char, store),
+        Err("full")
+    );
+    assert_eq!(chars.next(), Some('c'));
+}
+
+#[test]
+fn compresses_before_conversion() {
+    let input = "pse qrs, thanks";
+    let mut bytes = input.bytes();
+    let mut decoder = Decoder::new();
+    let mut text = String::new();
+    let read_char = || decoder.decode(|| bytes.next());
+    let store = |char, _: &str| {
+        text.push(char);
+        Ok::<_, Infallible>(())
+    };
+    let normalizer = &mut Normalizer::default();
+    let compressor = &mut Compressor::new(Some(&DEFAULT_DICTIONARY), false);
+    let conversion = convert(256, Policy::Fail, normalizer, compressor, read_char, store).unwrap();
+    assert_eq!(text, "pse qrs, TNX");
+    assert_eq!(conversion.bytes_read, input.len());
+    assert_eq!(conversion.chars_converted, 12);
+    assert_eq!(conversion.stop, Stop::End);
+}
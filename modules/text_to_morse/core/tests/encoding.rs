@@ -0,0 +1,169 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+use text_to_morse_core::{
+    encoding::{ByteOrder, Decoder, Encoding, BOM},
+    utf8::{DecodeError, Sequence},
+};
+
+/// Decode all sequences of a byte sequence in the given encoding.
+fn decode_all(encoding: Encoding, bytes: &[u8]) -> Vec<Sequence> {
+    let mut bytes = bytes.iter().copied();
+    let mut decoder = Decoder::new(encoding, None);
+    std::iter::from_fn(|| decoder.decode(|| bytes.next())).collect()
+}
+
+/// Decode a byte sequence, replacing each failed sequence with U+FFFD.
+fn decode_lossy(encoding: Encoding, bytes: &[u8]) -> String {
+    decode_all(encoding, bytes)
+        .iter()
+        .map(|sequence| sequence.char.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// Expect a single failed sequence.
+fn failed(error: DecodeError, len: usize) -> Sequence {
+    Sequence {
+        char: Err(error),
+        len,
+    }
+}
+
+/// Encode text as UTF-16 in the given byte order.
+fn utf16(text: &str, byte_order: ByteOrder) -> Vec<u8> {
+    text.encode_utf16()
+        .flat_map(|unit| match byte_order {
+            ByteOrder::Little => unit.to_le_bytes(),
+            ByteOrder::Big => unit.to_be_bytes(),
+        })
+        .collect()
+}
+
+#[test]
+fn ids() {
+    for id in 0..4 {
+        assert_eq!(Encoding::from_id(id).map(Encoding::id), Some(id));
+    }
+    assert_eq!(Encoding::from_id(4), None);
+}
+
+#[test]
+fn utf8() {
+    let text = "Grüße \u{1F600}";
+    assert_eq!(decode_lossy(Encoding::Utf8, text.as_bytes()), text);
+}
+
+#[test]
+fn latin1() {
+    let bytes: Vec<u8> = (0..=255).collect();
+    let sequences = decode_all(Encoding::Latin1, &bytes);
+    assert_eq!(sequences.len(), 256);
+    for (byte, sequence) in bytes.iter().zip(sequences) {
+        assert_eq!(sequence.char, Ok(char::from(*byte)));
+        assert_eq!(sequence.len, 1);
+    }
+    assert_eq!(decode_lossy(Encoding::Latin1, b"Gr\xfc\xdfe"), "Grüße");
+}
+
+#[test]
+fn ascii_rejects_bytes_above_0x7f() {
+    assert_eq!(decode_lossy(Encoding::Ascii, b"SOS\x7f"), "SOS\u{7f}");
+    assert_eq!(
+        decode_all(Encoding::Ascii, "ä".as_bytes()),
+        [
+            failed(DecodeError::Invalid, 1),
+            failed(DecodeError::Invalid, 1)
+        ]
+    );
+}
+
+#[test]
+fn utf16_little_endian_without_bom() {
+    let text = "SOS ä\u{1F600}";
+    let bytes = utf16(text, ByteOrder::Little);
+    assert_eq!(decode_lossy(Encoding::Utf16, &bytes), text);
+
+    let lens: Vec<usize> = decode_all(Encoding::Utf16, &bytes)
+        .iter()
+        .map(|sequence| sequence.len)
+        .collect();
+    assert_eq!(lens, [2, 2, 2, 2, 2, 4]);
+}
+
+#[test]
+fn utf16_detects_bom() {
+    for byte_order in [ByteOrder::Little, ByteOrder::Big] {
+        let text = "\u{feff}SOS ä\u{1F600}";
+        let bytes = utf16(text, byte_order);
+        assert_eq!(decode_lossy(Encoding::Utf16, &bytes), text);
+
+        let mut iter = bytes.iter().copied();
+        let mut decoder = Decoder::new(Encoding::Utf16, None);
+        assert_eq!(decoder.byte_order(), None);
+        let sequence = decoder.decode(|| iter.next()).unwrap();
+        assert_eq!((sequence.char, sequence.len), (Ok(BOM), 2));
+        assert_eq!(decoder.byte_order(), Some(byte_order));
+    }
+}
+
+#[test]
+fn utf16_keeps_given_byte_order() {
+    let bytes = utf16("SOS", ByteOrder::Big);
+    let mut iter = bytes.iter().copied();
+    let mut decoder = Decoder::new(Encoding::Utf16, Some(ByteOrder::Big));
+    let sequence = decoder.decode(|| iter.next()).unwrap();
+    assert_eq!(sequence.char, Ok('S'));
+}
+
+#[test]
+fn utf16_unpaired_surrogates() {
+    // A lone low surrogate.
+    assert_eq!(
+        decode_all(Encoding::Utf16, &[0x00, 0xdc, 0x41, 0x00]),
+        [
+            failed(DecodeError::Invalid, 2),
+            Sequence {
+                char: Ok('A'),
+                len: 2
+            }
+        ]
+    );
+
+    // A high surrogate followed by a character. The character is not lost.
+    let mut bytes = [0x3d, 0xd8, 0x41, 0x00].iter().copied();
+    let mut decoder = Decoder::new(Encoding::Utf16, None);
+    assert_eq!(
+        decoder.decode(|| bytes.next()),
+        Some(failed(DecodeError::Invalid, 2))
+    );
+    assert_eq!(decoder.pending(), 2);
+    let sequence = decoder.decode(|| bytes.next()).unwrap();
+    assert_eq!((sequence.char, sequence.len), (Ok('A'), 2));
+    assert_eq!(decoder.pending(), 0);
+}
+
+#[test]
+fn utf16_truncated() {
+    let truncated = |bytes: &[u8]| decode_all(Encoding::Utf16, bytes).pop();
+    assert_eq!(truncated(&[0x41]), Some(failed(DecodeError::Truncated, 1)));
+    assert_eq!(
+        truncated(&[0x3d, 0xd8]),
+        Some(failed(DecodeError::Truncated, 2))
+    );
+    assert_eq!(
+        truncated(&[0x3d, 0xd8, 0x00]),
+        Some(failed(DecodeError::Truncated, 3))
+    );
+}
+
+#[test]
+fn utf16_agrees_with_std() {
+    let units: [u16; 10] = [
+        0x0041, 0xd83d, 0xde00, 0xdc00, 0xd800, 0x0042, 0xdbff, 0xdfff, 0xd800, 0xd800,
+    ];
+    let bytes: Vec<u8> = units.iter().flat_map(|unit| unit.to_le_bytes()).collect();
+    let expected: String = char::decode_utf16(units)
+        .map(|char| char.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect();
+    assert_eq!(decode_lossy(Encoding::Utf16, &bytes), expected);
+}
@@ -0,0 +1,104 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+use text_to_morse_core::morse::{
+    char_from, is_letter_end, morse_code_from, Prosign, CHARACTERS, ERROR_CODE, MAX_MORSE_CODE_LEN,
+    MAX_PROSIGN_LEN, SYMBOLS,
+};
+
+// Characters with a morse code representation, each with a different morse code.
+const SUPPORTED: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789ÀÄÈÉÖÜßÑ.,:;?!-_()'=+/@\"";
+
+#[test]
+fn supported_chars_have_valid_codes() {
+    for char in SUPPORTED.chars() {
+        let code = morse_code_from(char);
+        assert_ne!(code, ERROR_CODE, "{char:?}");
+        assert!(code.len() <= MAX_MORSE_CODE_LEN, "{char:?}");
+        assert!(code.ends_with(' '), "{char:?}");
+        assert!(
+            code.trim_end()
+                .chars()
+                .all(|symbol| SYMBOLS.contains(symbol)),
+            "{char:?}"
+        );
+        assert_eq!(morse_code_from(char.to_ascii_lowercase()), code, "{char:?}");
+    }
+}
+
+#[test]
+fn supported_chars_have_unique_codes() {
+    for (index, char) in SUPPORTED.chars().enumerate() {
+        for other in SUPPORTED.chars().skip(index + 1) {
+            assert_ne!(
+                morse_code_from(char),
+                morse_code_from(other),
+                "{char:?} {other:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn supported_chars_convert_back() {
+    assert_eq!(CHARACTERS, SUPPORTED);
+    for char in SUPPORTED.chars() {
+        let code = morse_code_from(char).trim_end();
+        assert_eq!(char_from(code.as_bytes()), Some(char), "{char:?}");
+    }
+    assert_eq!(char_from(b".--.-"), Some('À'));
+}
+
+#[test]
+fn unknown_codes_convert_to_none() {
+    for code in [
+        "",
+        ".......",
+        "........",
+        "...---...",
+        "-.-.-.-.-",
+        "x",
+        ".- ",
+    ] {
+        assert_eq!(char_from(code.as_bytes()), None, "{code:?}");
+    }
+}
+
+#[test]
+fn whitespace_is_kept() {
+    assert_eq!(morse_code_from(' '), "   ");
+    for char in ['\n', '\r', '\t', '\0'] {
+        let mut buf = [0u8; 4];
+        assert_eq!(morse_code_from(char), char.encode_utf8(&mut buf));
+    }
+}
+
+#[test]
+fn unknown_chars_map_to_error_code() {
+    for char in ['#', '<', '€', 'ÿ', '\u{1F600}', '\u{10FFFF}'] {
+        assert_eq!(morse_code_from(char), ERROR_CODE, "{char:?}");
+    }
+}
+
+#[test]
+fn every_code_ends_a_letter() {
+    for char in (0..=0x2ff).filter_map(char::from_u32) {
+        let (last, rest) = morse_code_from(char).as_bytes().split_last().unwrap();
+        assert!(is_letter_end(*last), "{char:?}");
+        if char != ' ' {
+            assert!(!rest.iter().any(|byte| is_letter_end(*byte)), "{char:?}");
+        }
+    }
+}
+
+#[test]
+fn prosigns_round_trip() {
+    for id in 1..=5 {
+        let prosign = Prosign::from_id(id).unwrap();
+        assert_eq!(prosign.id(), id);
+        assert!(prosign.morse_code().len() <= MAX_PROSIGN_LEN);
+        assert!(prosign.morse_code().ends_with(' '));
+    }
+    assert!(Prosign::from_id(0).is_none());
+    assert!(Prosign::from_id(6).is_none());
+}
@@ -0,0 +1,138 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+use text_to_morse_core::normalize::{CutNumbers, Normalizer, Stages, State};
+
+/// Normalize a text with the given stages and the common cut numbers.
+fn normalize(text: &str, stages: Stages) -> String {
+    normalize_with(text, stages, CutNumbers::default())
+}
+
+/// Normalize a text with the given stages and cut numbers.
+fn normalize_with(text: &str, stages: Stages, cut_numbers: CutNumbers) -> String {
+    let mut normalizer = Normalizer::new(stages, cut_numbers, State::default());
+    text.chars()
+        .filter_map(|char| normalizer.normalize(char))
+        .collect()
+}
+
+/// Combine stages.
+fn stages(stages: &[Stages]) -> Stages {
+    let bits = stages.iter().fold(0, |bits, stage| bits | stage.bits());
+    Stages::from_bits(bits).unwrap()
+}
+
+#[test]
+fn bits() {
+    assert_eq!(Stages::from_bits(0), Some(Stages::default()));
+    assert_eq!(Stages::from_bits(0x3f), Some(Stages::ALL));
+    assert_eq!(Stages::from_bits(0x40), None);
+    assert!(Stages::ALL.contains(Stages::CUT_NUMBERS));
+    assert!(!Stages::CUT_NUMBERS.contains(Stages::ALL));
+}
+
+#[test]
+fn disabled() {
+    let text = "“SOS”  \t 0\r\n";
+    assert_eq!(normalize(text, Stages::default()), text);
+}
+
+#[test]
+fn collapses_whitespace() {
+    let stage = Stages::COLLAPSE_WHITESPACE;
+    assert_eq!(normalize("a  \t b", stage), "a b");
+    assert_eq!(normalize("a \n\n b\r\n", stage), "a \n\n b\r\n");
+    assert_eq!(normalize("a\u{a0}\u{a0}b", stage), "a b");
+}
+
+#[test]
+fn collapses_whitespace_across_calls() {
+    let stage = Stages::COLLAPSE_WHITESPACE;
+    let mut normalizer = Normalizer::new(stage, CutNumbers::default(), State::default());
+    assert_eq!(normalizer.normalize(' '), Some(' '));
+    assert!(normalizer.state().whitespace);
+
+    let mut normalizer = Normalizer::new(stage, CutNumbers::default(), normalizer.state());
+    assert_eq!(normalizer.normalize(' '), None);
+    assert_eq!(normalizer.normalize('a'), Some('a'));
+    assert!(!normalizer.state().whitespace);
+}
+
+#[test]
+fn maps_typography() {
+    let stage = Stages::MAP_TYPOGRAPHY;
+    assert_eq!(normalize("‘it’s’ „“ «»", stage), "'it's' \"\" \"\"");
+    assert_eq!(normalize("a–b—c−d", stage), "a-b-c-d");
+    assert_eq!(normalize("1\u{a0}000\u{202f}€", stage), "1 000 €");
+}
+
+#[test]
+fn cuts_numbers() {
+    assert_eq!(normalize("0123456789", Stages::CUT_NUMBERS), "TAUV4E6BDN");
+    assert_eq!(normalize("5nn", Stages::CUT_NUMBERS), "Enn");
+}
+
+#[test]
+fn cuts_numbers_in_fields() {
+    let stage = Stages::CUT_FIELDS;
+    assert_eq!(
+        normalize("ur 599 [599] 073 [073]", stage),
+        "ur 599 ENN 073 TBV"
+    );
+    assert_eq!(normalize("[1]", Stages::default()), "[1]");
+
+    // Fields continue across calls.
+    let mut normalizer = Normalizer::new(stage, CutNumbers::default(), State::default());
+    assert_eq!(normalizer.normalize('['), None);
+    let mut normalizer = Normalizer::new(stage, CutNumbers::default(), normalizer.state());
+    assert_eq!(normalizer.normalize('9'), Some('N'));
+    assert_eq!(normalizer.normalize(']'), None);
+    assert_eq!(normalizer.normalize('9'), Some('9'));
+}
+
+#[test]
+fn cut_number_tables() {
+    let cut_numbers = CutNumbers::from_letters(*b"oau3456bdn").unwrap();
+    assert_eq!(&cut_numbers.letters(), b"OAU3456BDN");
+    assert_eq!(
+        normalize_with("0123456789", Stages::CUT_NUMBERS, cut_numbers),
+        "OAU3456BDN"
+    );
+
+    // Entries are letters or their own digit, each letter substitutes a single digit.
+    assert_eq!(
+        CutNumbers::from_letters(*b"TAUV4E6BD9"),
+        CutNumbers::from_letters(*b"tauv4e6bd9")
+    );
+    assert_eq!(CutNumbers::from_letters(*b"TAUV5E6BDN"), None);
+    assert_eq!(CutNumbers::from_letters(*b"TAUV?E6BDN"), None);
+    assert_eq!(CutNumbers::from_letters(*b"TAUVTE6BDN"), None);
+}
+
+#[test]
+fn expands_cut_numbers() {
+    let cut_numbers = CutNumbers::default();
+    let expanded: String = "ENN TBV 4"
+        .chars()
+        .map(|char| cut_numbers.expand(char))
+        .collect();
+    assert_eq!(expanded, "599 073 4");
+    assert_eq!(cut_numbers.expand('n'), '9');
+    assert_eq!(cut_numbers.expand('X'), 'X');
+}
+
+#[test]
+fn strips_controls() {
+    let stage = Stages::STRIP_CONTROLS;
+    assert_eq!(normalize("a\0b\r\n\tc\u{7f}\u{85}", stage), "ab\nc");
+}
+
+#[test]
+fn applies_stages_in_order() {
+    // Tabs are collapsed into spaces before controls are stripped.
+    let text = "“599”\t\t\u{a0}tnx [1]\r\n";
+    assert_eq!(normalize(text, Stages::ALL), "\"ENN\" tnx A\n");
+
+    let without_whitespace = stages(&[Stages::MAP_TYPOGRAPHY, Stages::STRIP_CONTROLS]);
+    assert_eq!(normalize(text, without_whitespace), "\"599\" tnx [1]\n");
+}
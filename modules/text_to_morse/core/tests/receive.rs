@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+use text_to_morse_core::{
+    morse::{morse_code_from, CHARACTERS, ERROR_CODE},
+    normalize::CutNumbers,
+    receive::Receiver,
+};
+
+/// Receive morse code byte by byte.
+fn receive(morse_code: &str) -> String {
+    let mut receiver = Receiver::new();
+    let mut text = String::new();
+    for byte in morse_code.bytes() {
+        receiver.push(byte, |char| text.push(char));
+    }
+    text
+}
+
+/// Convert text into morse code.
+fn convert(text: &str) -> String {
+    text.chars().map(morse_code_from).collect()
+}
+
+#[test]
+fn receives_converted_text() {
+    for text in [
+        "SOS",
+        "Hello, World!\n",
+        "a  b\tc\r\n\0",
+        " leading",
+        "ÄÖÜ?",
+    ] {
+        assert_eq!(receive(&convert(text)), text.to_uppercase(), "{text:?}");
+    }
+    assert_eq!(receive(&convert(CHARACTERS)), CHARACTERS);
+}
+
+#[test]
+fn receives_word_gaps() {
+    assert_eq!(receive("... --- ...    ... "), "SOS S");
+    assert_eq!(receive("      "), "  ");
+    assert_eq!(receive(".-   "), "A");
+    assert_eq!(receive(".-    "), "A ");
+    assert_eq!(receive(".-       "), "A  ");
+}
+
+#[test]
+fn unknown_letters_become_replacement_characters() {
+    let text = receive(&convert("#a€"));
+    assert_eq!(text, "\u{fffd}A\u{fffd}");
+    assert_eq!(receive(ERROR_CODE), "\u{fffd}");
+    assert_eq!(receive(".-.-.-.-.-.- .- "), "\u{fffd}A");
+}
+
+#[test]
+fn control_characters_finish_letters() {
+    assert_eq!(receive("...\n---\r...\t.-\0"), "S\nO\rS\tA\0");
+    assert_eq!(receive("\n\n"), "\n\n");
+}
+
+#[test]
+fn other_bytes_are_ignored() {
+    assert_eq!(receive("..x. ÄbC-.-. "), "SC");
+    assert!(!{
+        let mut receiver = Receiver::new();
+        receiver.push(b'x', |_| unreachable!());
+        receiver.in_letter()
+    });
+}
+
+#[test]
+fn letters_continue_across_pushes() {
+    let mut receiver = Receiver::new();
+    let mut text = String::new();
+    for byte in ".-.".bytes() {
+        receiver.push(byte, |char| text.push(char));
+    }
+    assert!(receiver.in_letter());
+    assert!(text.is_empty());
+    receiver.push(b' ', |char| text.push(char));
+    assert!(!receiver.in_letter());
+    assert_eq!(text, "R");
+}
+
+#[test]
+fn expands_cut_numbers_if_enabled() {
+    let mut receiver = Receiver::new();
+    assert_eq!(receiver.expansion(), None);
+    receiver.set_expansion(Some(CutNumbers::default()));
+    assert_eq!(receiver.expansion(), Some(CutNumbers::default()));
+
+    let mut text = String::new();
+    for byte in convert("5NN TT1 4 X?").bytes() {
+        receiver.push(byte, |char| text.push(char));
+    }
+    assert_eq!(text, "599 001 4 X?");
+
+    // A reset drops the unfinished letter, but keeps the expansion.
+    receiver.push(b'-', |_| unreachable!());
+    receiver.reset();
+    assert!(!receiver.in_letter());
+    receiver.push(b'.', |_| unreachable!());
+    receiver.push(b' ', |char| assert_eq!(char, '5'));
+
+    receiver.set_expansion(None);
+    receiver.push(b'-', |_| unreachable!());
+    receiver.push(b' ', |char| assert_eq!(char, 'T'));
+}
@@ -0,0 +1,124 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+use std::mem::MaybeUninit;
+use text_to_morse_core::ringbuffer::{Error, Ring, Ringbuffer};
+
+#[test]
+fn empty() {
+    let mut queue = Ringbuffer::<u8, 4>::new();
+    assert!(queue.is_empty());
+    assert_eq!(queue.len(), 0);
+    assert_eq!(queue.free(), 4);
+    assert_eq!(queue.peek(), None);
+    assert_eq!(queue.try_pop(), Err(Error::Empty));
+
+    queue.try_push(1).unwrap();
+    assert_eq!(queue.try_pop(), Ok(1));
+    assert_eq!(queue.try_pop(), Err(Error::Empty));
+}
+
+#[test]
+fn full() {
+    let mut queue = Ringbuffer::<u8, 4>::new();
+    for byte in 0..4 {
+        queue.try_push(byte).unwrap();
+    }
+    assert!(queue.is_full());
+    assert_eq!(queue.try_push(4), Err(Error::Full));
+    assert_eq!(queue.push_overwrite(4), Some(0));
+    assert!(queue.iter().copied().eq([1, 2, 3, 4]));
+}
+
+#[test]
+fn wraparound() {
+    let mut queue = Ringbuffer::<u8, 4>::new();
+    for byte in 0..3 {
+        queue.try_push(byte).unwrap();
+    }
+    assert_eq!(queue.try_pop(), Ok(0));
+    assert_eq!(queue.try_pop(), Ok(1));
+    for byte in 3..6 {
+        queue.try_push(byte).unwrap();
+    }
+    assert_eq!(queue.write_pos(), 2);
+    assert_eq!(queue.read_pos(), 2);
+    assert_eq!(queue.get(3), Some(&5));
+    assert_eq!(queue.get(4), None);
+    for byte in 2..6 {
+        assert_eq!(queue.try_pop(), Ok(byte));
+    }
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn pop_until_boundary() {
+    let mut queue = Ringbuffer::<u8, 8>::new();
+    for byte in b"ab cd" {
+        queue.try_push(*byte).unwrap();
+    }
+    assert_eq!(queue.pop_until(|byte| *byte == b' '), 3);
+    assert!(queue.iter().copied().eq(*b"cd"));
+    assert_eq!(queue.pop_until(|byte| *byte == b' '), 2);
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn drain_empties() {
+    let mut queue = Ringbuffer::<u8, 4>::new();
+    for byte in 0..4 {
+        queue.try_push(byte).unwrap();
+    }
+    assert_eq!(queue.drain().take(1).collect::<Vec<_>>(), [0]);
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn matches_model() {
+    // Compare against VecDeque for a deterministic sequence of pushes and pops.
+    let mut queue = Ringbuffer::<u8, 5>::new();
+    let mut model = std::collections::VecDeque::new();
+    let mut state = 0x2545_f491u32;
+    for _ in 0..10_000 {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let byte = state as u8;
+        match state % 3 {
+            0 => assert_eq!(queue.try_pop().ok(), model.pop_front()),
+            1 => {
+                let pushed = queue.try_push(byte).is_ok();
+                assert_eq!(pushed, model.len() < 5);
+                if pushed {
+                    model.push_back(byte);
+                }
+            }
+            _ => {
+                let evicted = if model.len() == 5 {
+                    model.pop_front()
+                } else {
+                    None
+                };
+                model.push_back(byte);
+                assert_eq!(queue.push_overwrite(byte), evicted);
+            }
+        }
+        assert_eq!(queue.len(), model.len());
+        assert!(queue.iter().eq(model.iter()));
+    }
+}
+
+#[test]
+fn heap_slots() {
+    let mut queue = Ring::with_slots(vec![MaybeUninit::uninit(); 3]);
+    assert_eq!(queue.size(), 3);
+    for byte in 0..3 {
+        queue.try_push(byte).unwrap();
+    }
+    assert_eq!(queue.try_push(3), Err(Error::Full));
+    assert_eq!(queue.try_pop(), Ok(0));
+    queue.try_push(3).unwrap();
+    assert_eq!(queue.write_pos(), 1);
+    assert!(queue.drain().eq([1, 2, 3]));
+    assert!(queue.is_empty());
+}
@@ -0,0 +1,173 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+use text_to_morse_core::utf8::{sequence_len, DecodeError, Decoder, Policy, Sequence};
+
+/// Decode the first sequence of a byte sequence.
+fn decode(bytes: &[u8]) -> Option<Sequence> {
+    let mut bytes = bytes.iter().copied();
+    Decoder::new().decode(|| bytes.next())
+}
+
+/// Decode all sequences of a byte sequence.
+fn decode_all(bytes: &[u8]) -> Vec<Sequence> {
+    let mut bytes = bytes.iter().copied();
+    let mut decoder = Decoder::new();
+    std::iter::from_fn(|| decoder.decode(|| bytes.next())).collect()
+}
+
+/// Decode a byte sequence, replacing each failed sequence with U+FFFD.
+fn decode_lossy(bytes: &[u8]) -> String {
+    decode_all(bytes)
+        .iter()
+        .map(|sequence| sequence.char.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// Expect a single invalid sequence of a byte sequence.
+fn invalid(len: usize) -> Option<Sequence> {
+    Some(Sequence {
+        char: Err(DecodeError::Invalid),
+        len,
+    })
+}
+
+#[test]
+fn valid() {
+    for char in [
+        '\0',
+        'a',
+        '\u{7f}',
+        'ß',
+        '€',
+        '\u{d7ff}',
+        '\u{e000}',
+        '\u{1F600}',
+        '\u{10ffff}',
+    ] {
+        let mut buf = [0u8; 4];
+        let bytes = char.encode_utf8(&mut buf).as_bytes();
+        assert_eq!(sequence_len(bytes[0]), Some(bytes.len()));
+        assert_eq!(
+            decode(bytes),
+            Some(Sequence {
+                char: Ok(char),
+                len: bytes.len()
+            })
+        );
+    }
+}
+
+#[test]
+fn consumes_only_one_char() {
+    let mut bytes = "äb".bytes();
+    let mut decoder = Decoder::new();
+    assert_eq!(decoder.decode(|| bytes.next()).unwrap().char, Ok('ä'));
+    assert_eq!(decoder.pending(), 0);
+    assert_eq!(bytes.next(), Some(b'b'));
+}
+
+#[test]
+fn invalid_start() {
+    for byte in [0x80, 0xbf, 0xf8, 0xfc, 0xfe, 0xff] {
+        assert_eq!(sequence_len(byte), None);
+        assert_eq!(decode(&[byte, 0x80, 0x80, 0x80]), invalid(1));
+    }
+}
+
+#[test]
+fn overlong() {
+    for bytes in [
+        &[0xc0, 0xaf][..],
+        &[0xc1, 0xbf],
+        &[0xe0, 0x80, 0xaf],
+        &[0xe0, 0x9f, 0xbf],
+        &[0xf0, 0x80, 0x80, 0xaf],
+        &[0xf0, 0x8f, 0xbf, 0xbf],
+    ] {
+        assert_eq!(decode(bytes), invalid(1), "{bytes:x?}");
+    }
+}
+
+#[test]
+fn truncated() {
+    assert_eq!(decode(&[]), None);
+    for bytes in [&[0xc3][..], &[0xe2, 0x82], &[0xf0, 0x9f, 0x98]] {
+        let truncated = Sequence {
+            char: Err(DecodeError::Truncated),
+            len: bytes.len(),
+        };
+        assert_eq!(decode(bytes), Some(truncated), "{bytes:x?}");
+    }
+}
+
+#[test]
+fn surrogates_and_out_of_range() {
+    for bytes in [
+        &[0xed, 0xa0, 0x80][..],
+        &[0xed, 0xaf, 0xbf],
+        &[0xed, 0xb0, 0x80],
+        &[0xed, 0xbf, 0xbf],
+        &[0xf4, 0x90, 0x80, 0x80],
+        &[0xf7, 0xbf, 0xbf, 0xbf],
+    ] {
+        assert_eq!(decode(bytes), invalid(1), "{bytes:x?}");
+    }
+}
+
+#[test]
+fn invalid_sequence_ends_before_unexpected_byte() {
+    let mut bytes = [0xe2, 0x82, 0x41].iter().copied();
+    let mut decoder = Decoder::new();
+    assert_eq!(decoder.decode(|| bytes.next()), invalid(2));
+    assert_eq!(decoder.pending(), 1);
+    assert_eq!(decoder.decode(|| bytes.next()).unwrap().char, Ok('A'));
+    assert_eq!(decoder.decode(|| bytes.next()), None);
+
+    // Example of Unicode chapter 3.9, table 3-8.
+    let bytes = [
+        0x61, 0xf1, 0x80, 0x80, 0xe1, 0x80, 0xc2, 0x62, 0x80, 0x63, 0x80, 0xbf, 0x64,
+    ];
+    assert_eq!(
+        decode_lossy(&bytes),
+        "a\u{fffd}\u{fffd}\u{fffd}b\u{fffd}c\u{fffd}\u{fffd}d"
+    );
+    let lens: Vec<usize> = decode_all(&bytes).iter().map(|seq| seq.len).collect();
+    assert_eq!(lens, [1, 3, 2, 1, 1, 1, 1, 1, 1, 1]);
+}
+
+#[test]
+fn policy_ids() {
+    for policy in [Policy::Fail, Policy::Replace, Policy::Skip] {
+        assert_eq!(Policy::from_id(policy.id()), Some(policy));
+    }
+    assert_eq!(Policy::from_id(3), None);
+}
+
+#[test]
+fn agrees_with_std() {
+    // Every 1 and 2 byte sequence and all 3 byte sequences starting with 0xe0 or 0xed.
+    let mut sequences = Vec::new();
+    for first in 0..=255u8 {
+        sequences.push(vec![first]);
+        for second in 0..=255u8 {
+            sequences.push(vec![first, second]);
+            if first == 0xe0 || first == 0xed {
+                for third in 0..=255u8 {
+                    sequences.push(vec![first, second, third]);
+                }
+            }
+        }
+    }
+
+    // The lossy conversion of std replaces maximal subparts as well.
+    for bytes in sequences {
+        assert_eq!(
+            decode_lossy(&bytes),
+            String::from_utf8_lossy(&bytes),
+            "{bytes:x?}"
+        );
+        let len: usize = decode_all(&bytes).iter().map(|seq| seq.len).sum();
+        assert_eq!(len, bytes.len(), "{bytes:x?}");
+    }
+}
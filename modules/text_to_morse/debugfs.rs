@@ -0,0 +1,265 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+//! debugfs interface to inspect devices and to inject faults.
+//!
+//! Each device gets its own directory /sys/kernel/debug/text_to_morse/text_to_morse<id>/,
+//! named after the id of the device, containing the following files:
+//! * state: Dump of the inner device state, including the raw queue contents.
+//! * fail_push: Countdown until storing morse code fails with ENOMEM.
+//! * fail_read_char: Countdown until reading a char from user space fails with EINVAL.
+//! * wakeup_delay_ms: Delay in milliseconds before waiting readers/writers are woken up.
+//!
+//! A countdown of n lets the n-th attempt after it was set fail once, 0 disables the fault.
+//! This way the partial write error paths are reachable, e.g. writing "abc" after setting
+//! fail_read_char to 3 stores "ab" and returns 2.
+
+use super::{compress::DEFAULT_DICTIONARY, fault::Faults, Device};
+use alloc::vec::Vec;
+use core::{
+    ffi::{c_char, c_void},
+    fmt::{self, Write},
+    ptr,
+    sync::atomic::AtomicU32,
+};
+use kernel::{bindings, c_str, fmt, prelude::*, str::CString};
+
+// Name of the debugfs directory and prefix of all device directories.
+const NAME: &str = "text_to_morse";
+
+/// debugfs directory of the module, removed on drop.
+pub(crate) struct Debugfs {
+    root: *mut bindings::dentry,          // Directory containing device directories
+    fops: Box<bindings::file_operations>, // File operations of all state files
+}
+
+// SAFETY: The raw pointer is only accessed on creation and on drop. debugfs itself
+// synchronizes accesses to the dentries.
+unsafe impl Send for Debugfs {}
+
+// SAFETY: Shared references only pass the raw pointer to debugfs, which synchronizes
+// all accesses.
+unsafe impl Sync for Debugfs {}
+
+impl Debugfs {
+    /// Try to create the debugfs directory of the module.
+    ///
+    /// # Arguments:
+    /// * module: The module owning the created files.
+    ///
+    /// # Returns:
+    /// On success, an Ok containing the created directory,
+    /// on failure an Err containing return code ENOMEM.
+    ///
+    /// # Notes:
+    /// Failures of debugfs itself are not treated as errors. The module works without it.
+    pub(crate) fn try_new(module: &'static ThisModule) -> Result<Self> {
+        // SAFETY: file_operations is a plain C struct. All zeroes is a valid value.
+        let mut fops: bindings::file_operations = unsafe { core::mem::zeroed() };
+        fops.owner = module.as_ptr();
+        fops.open = Some(bindings::simple_open);
+        fops.read = Some(read_state);
+        fops.llseek = Some(bindings::default_llseek);
+        let fops = Box::try_new(fops)?;
+
+        let name = CString::try_from_fmt(fmt!("{}", NAME))?;
+
+        // SAFETY: name is copied by debugfs_create_dir. A NULL parent is the debugfs root.
+        let root = unsafe { bindings::debugfs_create_dir(name.as_char_ptr(), ptr::null_mut()) };
+
+        Ok(Self { root, fops })
+    }
+
+    /// Try to create the debugfs directory of a device.
+    ///
+    /// # Arguments:
+    /// * id: The id of the device to create a directory for.
+    ///
+    /// # Returns:
+    /// On success, an Ok containing the created directory,
+    /// on failure an Err containing return code ENOMEM.
+    ///
+    /// # Notes:
+    /// The directory must be dropped before the Debugfs object it was created by.
+    pub(crate) fn try_add_device(&self, id: u16) -> Result<DebugfsDir> {
+        let name = CString::try_from_fmt(fmt!("{}{}", NAME, id))?;
+        let faults = Faults::of(id);
+
+        // SAFETY: name is copied by debugfs_create_dir. debugfs handles invalid parents
+        // gracefully.
+        let dir = unsafe { bindings::debugfs_create_dir(name.as_char_ptr(), self.root) };
+
+        // SAFETY: The state file gets the device id as private data, it is never dereferenced.
+        // The file operations outlive the directory. The fault counters are static, so they
+        // outlive the files. AtomicU32 has the same layout as u32.
+        unsafe {
+            bindings::debugfs_create_file(
+                c_str!("state").as_char_ptr(),
+                0o444,
+                dir,
+                id as usize as *mut c_void,
+                &*self.fops,
+            );
+            bindings::debugfs_create_u32(
+                c_str!("fail_push").as_char_ptr(),
+                0o644,
+                dir,
+                counter_ptr(&faults.fail_push),
+            );
+            bindings::debugfs_create_u32(
+                c_str!("fail_read_char").as_char_ptr(),
+                0o644,
+                dir,
+                counter_ptr(&faults.fail_read_char),
+            );
+            bindings::debugfs_create_u32(
+                c_str!("wakeup_delay_ms").as_char_ptr(),
+                0o644,
+                dir,
+                counter_ptr(&faults.wakeup_delay_ms),
+            );
+        }
+
+        Ok(DebugfsDir { dir })
+    }
+}
+
+impl Drop for Debugfs {
+    fn drop(&mut self) {
+        // SAFETY: root was created by debugfs_create_dir and is removed only once. Removal
+        // waits until all open files finished their current operation.
+        unsafe { bindings::debugfs_remove(self.root) };
+    }
+}
+
+/// debugfs directory of a device, removed on drop.
+pub(crate) struct DebugfsDir {
+    dir: *mut bindings::dentry, // Directory containing all device files
+}
+
+// SAFETY: The raw pointer is only accessed on creation and on drop.
+unsafe impl Send for DebugfsDir {}
+
+// SAFETY: DebugfsDir offers no methods accessing the raw pointer.
+unsafe impl Sync for DebugfsDir {}
+
+impl Drop for DebugfsDir {
+    fn drop(&mut self) {
+        // SAFETY: dir was created by debugfs_create_dir and is removed only once. Removal
+        // waits until all open files finished their current operation.
+        unsafe { bindings::debugfs_remove(self.dir) };
+    }
+}
+
+/// Get a raw pointer to a fault counter, as expected by debugfs_create_u32.
+fn counter_ptr(counter: &AtomicU32) -> *mut u32 {
+    counter as *const AtomicU32 as *mut u32
+}
+
+/// Writer formatting text into a dynamically growing buffer.
+struct BufferWriter {
+    buf: Vec<u8>, // Buffer containing formatted text
+}
+
+impl Write for BufferWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.buf
+            .try_extend_from_slice(s.as_bytes())
+            .map_err(|_| fmt::Error)
+    }
+}
+
+/// Format the inner state of a device
+///
+/// # Arguments:
+/// * device: The device to format the state of.
+/// * out: The writer to format the state into.
+fn write_state(device: &Device, out: &mut impl Write) -> fmt::Result {
+    let inner = device.inner.lock();
+    writeln!(out, "id: {}", device.id)?;
+    writeln!(out, "overwrite: {}", inner.overwrite)?;
+    writeln!(out, "broadcast: {}", inner.broadcast)?;
+    writeln!(out, "multi_writer: {}", inner.multi_writer)?;
+    writeln!(out, "private_sessions: {}", inner.private_sessions)?;
+    writeln!(out, "writers: {}", inner.writers)?;
+    writeln!(out, "last_writer: {:?}", inner.last_writer)?;
+    writeln!(out, "separator: {}", inner.separator.map(|s| s.id()).unwrap_or(0))?;
+    writeln!(out, "utf8_policy: {}", inner.policy.id())?;
+    writeln!(out, "encoding: {}", inner.encoding.id())?;
+    writeln!(out, "normalization: {:#x}", inner.normalization.bits())?;
+    let cut_numbers = inner.cut_numbers.letters();
+    let cut_numbers = core::str::from_utf8(&cut_numbers).unwrap_or("?");
+    writeln!(out, "cut_numbers: {}", cut_numbers)?;
+    let dictionary = inner.dictionary.as_deref().unwrap_or(&DEFAULT_DICTIONARY);
+    writeln!(out, "abbreviations: {}", dictionary.len())?;
+    for (phrase, abbreviation) in dictionary.iter() {
+        let phrase = core::str::from_utf8(phrase).unwrap_or("?");
+        let abbreviation = core::str::from_utf8(abbreviation).unwrap_or("?");
+        writeln!(out, "  {}: {}", phrase, abbreviation)?;
+    }
+    writeln!(out, "dropped: {}", inner.dropped)?;
+    writeln!(out, "base: {}", inner.base)?;
+    writeln!(out, "readers: {}", inner.readers.len())?;
+    for reader in inner.readers.iter() {
+        writeln!(
+            out,
+            "  id: {} cursor: {} lagged: {} rest: {}",
+            reader.id,
+            reader.cursor,
+            reader.lagged,
+            reader.rest.len()
+        )?;
+    }
+    writeln!(out, "queue.rpos: {}", inner.queue.read_pos())?;
+    writeln!(out, "queue.wpos: {}", inner.queue.write_pos())?;
+    writeln!(out, "queue.used: {}", inner.queue.len())?;
+    write!(out, "queue: \"")?;
+    for byte in inner.queue.iter() {
+        write!(out, "{}", (*byte as char).escape_default())?;
+    }
+    writeln!(out, "\"")
+}
+
+/// Read callback of all state files
+///
+/// # Arguments:
+/// * file: The opened state file. Its private data contains the device id.
+/// * buf: User space buffer to copy the state into.
+/// * count: Size of buf.
+/// * ppos: Position in the state to copy from.
+///
+/// # Returns:
+/// On success, the number of bytes copied into buf, on failure a negative error code:
+/// * ENODEV: The device was removed.
+/// * ENOMEM: The state buffer must be allocated and this fails.
+/// * EFAULT: The state could not be copied to user space.
+unsafe extern "C" fn read_state(
+    file: *mut bindings::file,
+    buf: *mut c_char,
+    count: usize,
+    ppos: *mut bindings::loff_t,
+) -> isize {
+    // SAFETY: simple_open stored the device id given on creation as private data.
+    let id = unsafe { (*file).private_data } as usize as u16;
+    let device = match Device::get(id) {
+        Ok(device) => device,
+        Err(errno) => return errno.to_kernel_errno() as isize,
+    };
+
+    let mut out = BufferWriter { buf: Vec::new() };
+    if write_state(&device, &mut out).is_err() {
+        return ENOMEM.to_kernel_errno() as isize;
+    }
+
+    // SAFETY: buf is a user space buffer of count bytes and ppos is valid, both given by
+    // the VFS. The source is a kernel buffer of the given length.
+    unsafe {
+        bindings::simple_read_from_buffer(
+            buf as *mut c_void,
+            count,
+            ppos,
+            out.buf.as_ptr() as *const c_void,
+            out.buf.len(),
+        )
+    }
+}
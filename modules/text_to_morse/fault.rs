@@ -0,0 +1,93 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+//! Fault injection to exercise error paths, that are hard to trigger from user space.
+//!
+//! Each device has its own set of faults, shared with all private sessions of the device.
+//! The faults are configured via debugfs, see module debugfs.
+
+use super::MAX_DEVICES;
+use core::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+use kernel::delay::coarse_sleep;
+
+/// Faults injectable into a device.
+pub(crate) struct Faults {
+    pub(crate) fail_push: AtomicU32,       // Countdown until storing morse code fails (ENOMEM)
+    pub(crate) fail_read_char: AtomicU32,  // Countdown until reading a char fails (EINVAL)
+    pub(crate) wakeup_delay_ms: AtomicU32, // Delay before waking up waiting readers/writers
+}
+
+// Value to initialize the faults of all devices with.
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_FAULTS: Faults = Faults::new();
+
+// Faults of all devices, indexed by device id.
+static FAULTS: [Faults; MAX_DEVICES] = [NO_FAULTS; MAX_DEVICES];
+
+impl Faults {
+    /// Create a set of faults with all faults disabled.
+    const fn new() -> Self {
+        Self {
+            fail_push: AtomicU32::new(0),
+            fail_read_char: AtomicU32::new(0),
+            wakeup_delay_ms: AtomicU32::new(0),
+        }
+    }
+
+    /// Get the faults of a device
+    ///
+    /// # Arguments:
+    /// * id: The id of the device.
+    ///
+    /// # Returns:
+    /// A reference to the faults of the device.
+    pub(crate) fn of(id: u16) -> &'static Self {
+        &FAULTS[id as usize]
+    }
+
+    /// Disable all faults.
+    pub(crate) fn reset(&self) {
+        self.fail_push.store(0, Ordering::Relaxed);
+        self.fail_read_char.store(0, Ordering::Relaxed);
+        self.wakeup_delay_ms.store(0, Ordering::Relaxed);
+    }
+
+    /// Check if storing morse code shall fail.
+    ///
+    /// # Returns:
+    /// true if the fail_push countdown expired with this call, otherwise false.
+    pub(crate) fn fail_push(&self) -> bool {
+        countdown(&self.fail_push)
+    }
+
+    /// Check if reading a char shall fail.
+    ///
+    /// # Returns:
+    /// true if the fail_read_char countdown expired with this call, otherwise false.
+    pub(crate) fn fail_read_char(&self) -> bool {
+        countdown(&self.fail_read_char)
+    }
+
+    /// Sleep for the configured wakeup delay. Must not be called with a lock held.
+    pub(crate) fn delay_wakeup(&self) {
+        let delay = self.wakeup_delay_ms.load(Ordering::Relaxed);
+        if delay > 0 {
+            coarse_sleep(Duration::from_millis(delay as u64));
+        }
+    }
+}
+
+/// Decrement a fault countdown
+///
+/// # Arguments:
+/// * counter: The countdown to decrement. A value of 0 disables the fault.
+///
+/// # Returns:
+/// true if the countdown reached 0 with this call, otherwise false. The fault triggers
+/// once on the n-th call after the countdown was set to n.
+fn countdown(counter: &AtomicU32) -> bool {
+    counter.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1)) == Ok(1)
+}
@@ -0,0 +1,433 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+use super::{
+    compress::{Dictionary, DictionaryError, DEFAULT_DICTIONARY, MAX_ABBREV_LEN, MAX_PHRASE_LEN},
+    encoding::Encoding,
+    morse::Prosign,
+    normalize::{CutNumbers, Stages},
+    trace,
+    utf8::Policy,
+    Handle, Statistics, WRITE_CONDITION,
+};
+use core::{ffi::c_void, mem::size_of};
+use kernel::{
+    file::{File, IoctlHandler},
+    io_buffer::{IoBufferReader, IoBufferWriter},
+    ioctl::{_IO, _IOR, _IOW},
+    prelude::*,
+    sync::{Arc, UniqueArc},
+    user_ptr::{UserSlicePtr, UserSlicePtrReader, UserSlicePtrWriter},
+};
+
+// Ioctl type number of all commands supported by a Device.
+pub(crate) const IOCTL_TYPE: u32 = b'M' as u32;
+
+/// Get the overwrite mode of a Device. Argument: u32, 0 if disabled, 1 if enabled.
+pub(crate) const IOCTL_GET_OVERWRITE: u32 = _IOR::<u32>(IOCTL_TYPE, 0x01);
+
+/// Set the overwrite mode of a Device. Argument: u32, 0 to disable, everything else to enable.
+pub(crate) const IOCTL_SET_OVERWRITE: u32 = _IOW::<u32>(IOCTL_TYPE, 0x02);
+
+/// Get the number of bytes dropped in overwrite mode. Argument: u64.
+pub(crate) const IOCTL_GET_DROPPED: u32 = _IOR::<u64>(IOCTL_TYPE, 0x03);
+
+/// Discard all morse code currently stored in a Device. Argument: None.
+pub(crate) const IOCTL_FLUSH: u32 = _IO(IOCTL_TYPE, 0x04);
+
+/// Get the broadcast mode of a Device. Argument: u32, 0 if disabled, 1 if enabled.
+pub(crate) const IOCTL_GET_BROADCAST: u32 = _IOR::<u32>(IOCTL_TYPE, 0x05);
+
+/// Set the broadcast mode of a Device. Argument: u32, 0 to disable, everything else to enable.
+pub(crate) const IOCTL_SET_BROADCAST: u32 = _IOW::<u32>(IOCTL_TYPE, 0x06);
+
+/// Get the number of bytes dropped before the calling reader read them. Argument: u64.
+pub(crate) const IOCTL_GET_LAG: u32 = _IOR::<u64>(IOCTL_TYPE, 0x07);
+
+/// Get the multi writer mode of a Device. Argument: u32, 0 if disabled, 1 if enabled.
+pub(crate) const IOCTL_GET_MULTI_WRITER: u32 = _IOR::<u32>(IOCTL_TYPE, 0x08);
+
+/// Set the multi writer mode of a Device. Argument: u32, 0 to disable, everything else to enable.
+pub(crate) const IOCTL_SET_MULTI_WRITER: u32 = _IOW::<u32>(IOCTL_TYPE, 0x09);
+
+/// Get the prosign separating messages of different writers.
+/// Argument: u32, 0 if disabled, otherwise the prosign id (1: AR, 2: AS, 3: BT, 4: KN, 5: SK).
+pub(crate) const IOCTL_GET_SEPARATOR: u32 = _IOR::<u32>(IOCTL_TYPE, 0x0A);
+
+/// Set the prosign separating messages of different writers.
+/// Argument: u32, 0 to disable, otherwise the prosign id (1: AR, 2: AS, 3: BT, 4: KN, 5: SK).
+pub(crate) const IOCTL_SET_SEPARATOR: u32 = _IOW::<u32>(IOCTL_TYPE, 0x0B);
+
+/// Get the private session mode of a Device. Argument: u32, 0 if disabled, 1 if enabled.
+pub(crate) const IOCTL_GET_PRIVATE_SESSIONS: u32 = _IOR::<u32>(IOCTL_TYPE, 0x0C);
+
+/// Set the private session mode of a Device. Argument: u32, 0 to disable, everything else to
+/// enable. The mode applies to files opened afterwards.
+pub(crate) const IOCTL_SET_PRIVATE_SESSIONS: u32 = _IOW::<u32>(IOCTL_TYPE, 0x0D);
+
+/// Reset the statistics of a Device. Argument: None.
+pub(crate) const IOCTL_RESET_STATS: u32 = _IO(IOCTL_TYPE, 0x0E);
+
+/// Get the statistics of a Device. Argument: struct of 8 u64 values in the following order:
+/// bytes_in, chars_converted, bytes_out, unknown_chars, invalid_utf8, writer_blocks,
+/// reader_blocks, interrupts. See the sysfs attributes with the same names.
+pub(crate) const IOCTL_GET_STATS: u32 = _IOR::<Statistics>(IOCTL_TYPE, 0x0F);
+
+/// Get the handling of invalid UTF-8 written into a Device.
+/// Argument: u32, the policy id (0: Fail, 1: Replace with U+FFFD, 2: Skip).
+pub(crate) const IOCTL_GET_UTF8_POLICY: u32 = _IOR::<u32>(IOCTL_TYPE, 0x10);
+
+/// Set the handling of invalid UTF-8 written into a Device.
+/// Argument: u32, the policy id (0: Fail, 1: Replace with U+FFFD, 2: Skip).
+pub(crate) const IOCTL_SET_UTF8_POLICY: u32 = _IOW::<u32>(IOCTL_TYPE, 0x11);
+
+/// Get the encoding of text written into a Device.
+/// Argument: u32, the encoding id (0: UTF-8, 1: Latin-1, 2: UTF-16, 3: ASCII).
+pub(crate) const IOCTL_GET_ENCODING: u32 = _IOR::<u32>(IOCTL_TYPE, 0x12);
+
+/// Set the encoding of text written into a Device. The byte order of UTF-16 is detected
+/// again on the next write of each file of the device.
+/// Argument: u32, the encoding id (0: UTF-8, 1: Latin-1, 2: UTF-16, 3: ASCII).
+pub(crate) const IOCTL_SET_ENCODING: u32 = _IOW::<u32>(IOCTL_TYPE, 0x13);
+
+/// Get the normalization stages of text written into a Device.
+/// Argument: u32, bitmask of the stages (1: Collapse whitespace, 2: Map typography,
+/// 4: Cut numbers, 8: Strip controls, 16: Cut numbers in fields, 32: Compress phrases).
+pub(crate) const IOCTL_GET_NORMALIZATION: u32 = _IOR::<u32>(IOCTL_TYPE, 0x14);
+
+/// Set the normalization stages of text written into a Device.
+/// Argument: u32, bitmask of the stages (1: Collapse whitespace, 2: Map typography,
+/// 4: Cut numbers, 8: Strip controls, 16: Cut numbers in fields, 32: Compress phrases).
+pub(crate) const IOCTL_SET_NORMALIZATION: u32 = _IOW::<u32>(IOCTL_TYPE, 0x15);
+
+/// Get the cut numbers of a Device.
+/// Argument: u8[10], the letter substituting each digit, starting at 0.
+pub(crate) const IOCTL_GET_CUT_NUMBERS: u32 = _IOR::<[u8; 10]>(IOCTL_TYPE, 0x16);
+
+/// Set the cut numbers of a Device.
+/// Argument: u8[10], the letter substituting each digit, starting at 0. A digit is kept,
+/// if its entry is the digit itself. Each letter may substitute a single digit.
+pub(crate) const IOCTL_SET_CUT_NUMBERS: u32 = _IOW::<[u8; 10]>(IOCTL_TYPE, 0x17);
+
+/// Add an abbreviation replacing a phrase, if compression is enabled. An abbreviation of the
+/// same phrase is replaced. Argument: u8[32], the phrase in the first 24 bytes, followed by the
+/// abbreviation in the last 8 bytes. Both are padded with zeros. The phrase consists of ASCII
+/// words of letters and digits, separated by single spaces. The abbreviation consists of
+/// ASCII letters and digits and must not be longer than the phrase.
+pub(crate) const IOCTL_ADD_ABBREVIATION: u32 = _IOW::<[u8; 32]>(IOCTL_TYPE, 0x18);
+
+/// Remove all abbreviations of a Device, including the default ones. Argument: None.
+pub(crate) const IOCTL_CLEAR_ABBREVIATIONS: u32 = _IO(IOCTL_TYPE, 0x19);
+
+/// Get the expansion of received cut numbers of a tty with line discipline N_MORSE.
+/// Argument: u32, 0 if disabled, 1 if enabled.
+pub(crate) const IOCTL_GET_EXPAND_CUT_NUMBERS: u32 = _IOR::<u32>(IOCTL_TYPE, 0x1A);
+
+/// Set the expansion of received cut numbers of a tty with line discipline N_MORSE.
+/// Argument: u32, 0 to disable, everything else to enable. If enabled, each received letter
+/// substituting a digit by default is returned as the digit, e.g. "5NN" as "599".
+pub(crate) const IOCTL_SET_EXPAND_CUT_NUMBERS: u32 = _IOW::<u32>(IOCTL_TYPE, 0x1B);
+
+/// Get the number of bytes available for reading. Argument: int.
+/// Generic command, see include/uapi/asm-generic/ioctls.h.
+pub(crate) const FIONREAD: u32 = 0x541B;
+
+impl IoctlHandler for Handle {
+    type Target<'a> = &'a Handle;
+
+    /// Handle ioctl commands without encoded data transfer direction.
+    ///
+    /// # Arguments:
+    /// * handle: Reference to Handle of the Device the command is issued on.
+    /// * _file: Reference kernel file data structure.
+    /// * cmd: The ioctl command to handle.
+    /// * arg: The raw argument of the command.
+    ///
+    /// # Returns:
+    /// On success: An Ok containing 0, on failure an Err containing one of
+    /// the following error codes:
+    /// * ENOTTY: The given command is not supported.
+    /// * ENOMEM: The abbreviations must be allocated and this fails.
+    /// * EFAULT: Data could not be transferred to user space.
+    fn pure(handle: &Handle, _file: &File, cmd: u32, arg: usize) -> Result<i32> {
+        let device = &handle.device;
+        match cmd {
+            FIONREAD => {
+                let inner = device.inner.lock();
+                let available = match handle.reader {
+                    Some(reader) => inner.available(reader),
+                    None => inner.queue.len(),
+                };
+                drop(inner);
+
+                // SAFETY: arg is the user space pointer given to this command. It is
+                // accessed only once and all accesses are checked by UserSlicePtr.
+                let user_ptr = unsafe { UserSlicePtr::new(arg as *mut c_void, size_of::<i32>()) };
+                user_ptr.writer().write(&(available as i32))?;
+            }
+            IOCTL_FLUSH => {
+                let flushed = device.inner.lock().flush();
+                if flushed > 0 {
+                    trace::queue_empty(device.id);
+                }
+                log_info!("Flushed {} bytes from device {}.\n", flushed, device.id);
+
+                // Blocked writers are able to proceed now.
+                WRITE_CONDITION.notify_all();
+            }
+            IOCTL_RESET_STATS => {
+                device.inner.lock().stats = Statistics::default();
+                log_info!("Reset statistics of device {}.\n", device.id);
+            }
+            IOCTL_CLEAR_ABBREVIATIONS => {
+                let dictionary = Arc::try_new(Dictionary::new())?;
+                device.inner.lock().dictionary = Some(dictionary);
+                log_info!("Cleared abbreviations of device {}.\n", device.id);
+            }
+            _ => {
+                log_err!("Unsupported ioctl command {:#x}.\n", cmd);
+                return Err(ENOTTY);
+            }
+        }
+        Ok(0)
+    }
+
+    /// Handle ioctl commands transferring data from a Device to user space.
+    ///
+    /// # Arguments:
+    /// * handle: Reference to Handle of the Device to query.
+    /// * _file: Reference kernel file data structure.
+    /// * cmd: The ioctl command to handle.
+    /// * writer: Writer to transfer the requested data to user space.
+    ///
+    /// # Returns:
+    /// On success: An Ok containing 0, on failure an Err containing one of
+    /// the following error codes:
+    /// * ENOTTY: The given command is not supported.
+    /// * EBADF: IOCTL_GET_LAG was issued on a file not opened for reading.
+    /// * EFAULT: Data could not be transferred to user space.
+    fn read(
+        handle: &Handle,
+        _file: &File,
+        cmd: u32,
+        writer: &mut UserSlicePtrWriter,
+    ) -> Result<i32> {
+        let device = &handle.device;
+        match cmd {
+            IOCTL_GET_OVERWRITE => {
+                let overwrite = device.inner.lock().overwrite;
+                writer.write(&(overwrite as u32))?;
+            }
+            IOCTL_GET_DROPPED => {
+                let dropped = device.inner.lock().dropped;
+                writer.write(&dropped)?;
+            }
+            IOCTL_GET_BROADCAST => {
+                let broadcast = device.inner.lock().broadcast;
+                writer.write(&(broadcast as u32))?;
+            }
+            IOCTL_GET_LAG => {
+                let reader = handle.reader.ok_or(EBADF)?;
+                let lagged = device
+                    .inner
+                    .lock()
+                    .reader(reader)
+                    .map(|reader| reader.lagged)
+                    .unwrap_or(0);
+                writer.write(&lagged)?;
+            }
+            IOCTL_GET_MULTI_WRITER => {
+                let multi_writer = device.inner.lock().multi_writer;
+                writer.write(&(multi_writer as u32))?;
+            }
+            IOCTL_GET_SEPARATOR => {
+                let separator = device.inner.lock().separator;
+                writer.write(&separator.map(Prosign::id).unwrap_or(0))?;
+            }
+            IOCTL_GET_STATS => {
+                let stats = device.inner.lock().stats;
+                writer.write(&stats)?;
+            }
+            IOCTL_GET_PRIVATE_SESSIONS => {
+                let private_sessions = device.inner.lock().private_sessions;
+                writer.write(&(private_sessions as u32))?;
+            }
+            IOCTL_GET_UTF8_POLICY => {
+                let policy = device.inner.lock().policy;
+                writer.write(&policy.id())?;
+            }
+            IOCTL_GET_ENCODING => {
+                let encoding = device.inner.lock().encoding;
+                writer.write(&encoding.id())?;
+            }
+            IOCTL_GET_NORMALIZATION => {
+                let normalization = device.inner.lock().normalization;
+                writer.write(&normalization.bits())?;
+            }
+            IOCTL_GET_CUT_NUMBERS => {
+                let cut_numbers = device.inner.lock().cut_numbers;
+                writer.write_slice(&cut_numbers.letters())?;
+            }
+            _ => {
+                log_err!("Unsupported ioctl command {:#x}.\n", cmd);
+                return Err(ENOTTY);
+            }
+        }
+        Ok(0)
+    }
+
+    /// Handle ioctl commands transferring data from user space to a Device.
+    ///
+    /// # Arguments:
+    /// * handle: Reference to Handle of the Device to modify.
+    /// * _file: Reference kernel file data structure.
+    /// * cmd: The ioctl command to handle.
+    /// * reader: Reader to transfer the given data from user space.
+    ///
+    /// # Returns:
+    /// On success: An Ok containing 0, on failure an Err containing one of
+    /// the following error codes:
+    /// * ENOTTY: The given command is not supported.
+    /// * EBUSY: Broadcast mode can't be disabled while multiple readers access the Device.
+    /// * EBUSY: Multi writer mode can't be disabled while multiple writers access the Device.
+    /// * EINVAL: The given prosign id, policy id or encoding id is unknown.
+    /// * EINVAL: The given normalization stages contain unknown stages.
+    /// * EINVAL: The given cut numbers contain invalid or ambiguous letters.
+    /// * EINVAL: The given phrase or abbreviation is malformed.
+    /// * ENOSPC: The abbreviations contain the maximum number of entries.
+    /// * ENOMEM: The abbreviations must be allocated and this fails.
+    /// * EINVAL: Private session mode can't be set within a private session.
+    /// * EFAULT: Data could not be transferred from user space.
+    fn write(
+        handle: &Handle,
+        _file: &File,
+        cmd: u32,
+        reader: &mut UserSlicePtrReader,
+    ) -> Result<i32> {
+        let device = &handle.device;
+        match cmd {
+            IOCTL_SET_OVERWRITE => {
+                let overwrite = reader.read::<u32>()? != 0;
+                log_info!("Set overwrite mode of device {} to {}.\n", device.id, overwrite);
+                device.inner.lock().overwrite = overwrite;
+
+                // Blocked writers might be able to proceed now.
+                WRITE_CONDITION.notify_all();
+            }
+            IOCTL_SET_BROADCAST => {
+                let broadcast = reader.read::<u32>()? != 0;
+                let mut inner = device.inner.lock();
+                if !broadcast && inner.readers.len() > 1 {
+                    log_err!(
+                        "Failed to disable broadcast mode of device {}. Multiple readers.\n",
+                        device.id
+                    );
+                    return Err(EBUSY);
+                }
+                log_info!("Set broadcast mode of device {} to {}.\n", device.id, broadcast);
+                inner.broadcast = broadcast;
+            }
+            IOCTL_SET_MULTI_WRITER => {
+                let multi_writer = reader.read::<u32>()? != 0;
+                let mut inner = device.inner.lock();
+                if !multi_writer && inner.writers > 1 {
+                    log_err!(
+                        "Failed to disable multi writer mode of device {}. Multiple writers.\n",
+                        device.id
+                    );
+                    return Err(EBUSY);
+                }
+                log_info!("Set multi writer mode of device {} to {}.\n", device.id, multi_writer);
+                inner.multi_writer = multi_writer;
+            }
+            IOCTL_SET_SEPARATOR => {
+                let separator = match reader.read::<u32>()? {
+                    0 => None,
+                    id => Some(Prosign::from_id(id).ok_or(EINVAL)?),
+                };
+                log_info!("Set message separator of device {}.\n", device.id);
+                device.inner.lock().separator = separator;
+            }
+            IOCTL_SET_PRIVATE_SESSIONS => {
+                let private_sessions = reader.read::<u32>()? != 0;
+                let mut inner = device.inner.lock();
+                if inner.session {
+                    log_err!("Failed to set private session mode within a private session.\n");
+                    return Err(EINVAL);
+                }
+                log_info!(
+                    "Set private session mode of device {} to {}.\n",
+                    device.id,
+                    private_sessions
+                );
+                inner.private_sessions = private_sessions;
+            }
+            IOCTL_SET_UTF8_POLICY => {
+                let policy = Policy::from_id(reader.read::<u32>()?).ok_or(EINVAL)?;
+                log_info!("Set UTF-8 policy of device {} to {:?}.\n", device.id, policy);
+                device.inner.lock().policy = policy;
+            }
+            IOCTL_SET_ENCODING => {
+                let encoding = Encoding::from_id(reader.read::<u32>()?).ok_or(EINVAL)?;
+                log_info!("Set encoding of device {} to {:?}.\n", device.id, encoding);
+                // Restarts the byte order detection of all files, see DetectedOrder.
+                let mut inner = device.inner.lock();
+                inner.encoding = encoding;
+                inner.encoding_changes += 1;
+            }
+            IOCTL_SET_NORMALIZATION => {
+                let normalization = Stages::from_bits(reader.read::<u32>()?).ok_or(EINVAL)?;
+                log_info!(
+                    "Set normalization of device {} to {:#x}.\n",
+                    device.id,
+                    normalization.bits()
+                );
+                device.inner.lock().normalization = normalization;
+            }
+            IOCTL_SET_CUT_NUMBERS => {
+                let mut letters = [0u8; 10];
+                reader.read_slice(&mut letters)?;
+                let cut_numbers = CutNumbers::from_letters(letters).ok_or(EINVAL)?;
+                log_info!("Set cut numbers of device {}.\n", device.id);
+                device.inner.lock().cut_numbers = cut_numbers;
+            }
+            IOCTL_ADD_ABBREVIATION => {
+                let mut entry = [0u8; MAX_PHRASE_LEN + MAX_ABBREV_LEN];
+                reader.read_slice(&mut entry)?;
+                let (phrase, abbreviation) = entry.split_at(MAX_PHRASE_LEN);
+                let phrase = until_nul(phrase);
+                let abbreviation = until_nul(abbreviation);
+
+                // The dictionary is copied into its allocation, a copy on the stack would be
+                // too large.
+                let mut dictionary = UniqueArc::try_new(Dictionary::new())?;
+                let mut inner = device.inner.lock();
+                dictionary.clone_from(inner.dictionary.as_deref().unwrap_or(&DEFAULT_DICTIONARY));
+                dictionary
+                    .try_add(phrase, abbreviation)
+                    .map_err(|error| match error {
+                        DictionaryError::Full => ENOSPC,
+                        DictionaryError::Invalid => EINVAL,
+                    })?;
+                inner.dictionary = Some(dictionary.into());
+                log_info!("Added abbreviation to device {}.\n", device.id);
+            }
+            _ => {
+                log_err!("Unsupported ioctl command {:#x}.\n", cmd);
+                return Err(ENOTTY);
+            }
+        }
+        Ok(0)
+    }
+}
+
+/// Cut a zero padded byte string
+///
+/// # Arguments:
+/// * bytes: The byte string, padded with zeros.
+///
+/// # Returns:
+/// The bytes before the first zero, all bytes if there is none.
+fn until_nul(bytes: &[u8]) -> &[u8] {
+    bytes.split(|byte| *byte == 0).next().unwrap_or(bytes)
+}
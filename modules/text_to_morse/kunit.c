@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+/*
+ * KUnit suite of the module.
+ *
+ * Suites can only be declared via the C macros below, so the test cases are implemented in
+ * kunit.rs and only registered here. Failed checks are reported via text_to_morse_kunit_fail.
+ * The suite runs on module load. Its results are available under
+ * /sys/kernel/debug/kunit/text_to_morse/results.
+ */
+
+#include <kunit/test.h>
+
+void text_to_morse_test_morse_code_table(struct kunit *test);
+void text_to_morse_test_morse_code_unique(struct kunit *test);
+void text_to_morse_test_ringbuffer_empty(struct kunit *test);
+void text_to_morse_test_ringbuffer_full(struct kunit *test);
+void text_to_morse_test_ringbuffer_wraparound(struct kunit *test);
+void text_to_morse_test_read_char_valid(struct kunit *test);
+void text_to_morse_test_read_char_overlong(struct kunit *test);
+void text_to_morse_test_read_char_truncated(struct kunit *test);
+void text_to_morse_test_read_char_surrogate(struct kunit *test);
+void text_to_morse_test_read_char_encodings(struct kunit *test);
+
+void text_to_morse_kunit_fail(struct kunit *test, const char *file, u32 line, const char *check)
+{
+	KUNIT_FAIL(test, "%s:%u: Check failed: %s", file, line, check);
+}
+
+static struct kunit_case text_to_morse_test_cases[] = {
+	KUNIT_CASE(text_to_morse_test_morse_code_table),
+	KUNIT_CASE(text_to_morse_test_morse_code_unique),
+	KUNIT_CASE(text_to_morse_test_ringbuffer_empty),
+	KUNIT_CASE(text_to_morse_test_ringbuffer_full),
+	KUNIT_CASE(text_to_morse_test_ringbuffer_wraparound),
+	KUNIT_CASE(text_to_morse_test_read_char_valid),
+	KUNIT_CASE(text_to_morse_test_read_char_overlong),
+	KUNIT_CASE(text_to_morse_test_read_char_truncated),
+	KUNIT_CASE(text_to_morse_test_read_char_surrogate),
+	KUNIT_CASE(text_to_morse_test_read_char_encodings),
+	{}
+};
+
+static struct kunit_suite text_to_morse_test_suite = {
+	.name = "text_to_morse",
+	.test_cases = text_to_morse_test_cases,
+};
+
+kunit_test_suite(text_to_morse_test_suite);
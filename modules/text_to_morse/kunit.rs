@@ -0,0 +1,299 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+//! KUnit test cases of the module.
+//!
+//! The test cases are registered as suite text_to_morse in kunit.c and run on module load,
+//! if the kernel is built with CONFIG_KUNIT. Use `make test_module` to run them headless
+//! and to evaluate the results via kunit.py.
+
+use super::{
+    encoding::{ByteOrder, Decoder, Encoding},
+    morse::{morse_code_from, ERROR_CODE, MAX_MORSE_CODE_LEN, SYMBOLS},
+    ringbuffer::{Error, Ringbuffer},
+    try_read_char,
+    utf8::DecodeError,
+};
+use core::{ffi::c_char, ptr};
+use kernel::{io_buffer::IoBufferReader, prelude::*};
+
+/// Opaque struct kunit, passed to each test case.
+#[repr(C)]
+pub(crate) struct Kunit {
+    _private: [u8; 0],
+}
+
+extern "C" {
+    fn text_to_morse_kunit_fail(
+        test: *mut Kunit,
+        file: *const c_char,
+        line: u32,
+        check: *const c_char,
+    );
+}
+
+/// Fail the running test case, if a condition does not hold.
+macro_rules! check {
+    ($test:expr, $cond:expr) => {
+        if !$cond {
+            // SAFETY: test is passed by KUnit, both strings are NUL terminated.
+            unsafe {
+                text_to_morse_kunit_fail(
+                    $test,
+                    concat!(file!(), "\0").as_ptr() as *const c_char,
+                    line!(),
+                    concat!(stringify!($cond), "\0").as_ptr() as *const c_char,
+                )
+            };
+        }
+    };
+}
+
+// Characters with a morse code representation, each with a different morse code.
+const SUPPORTED: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789ÀÄÈÉÖÜßÑ.,:;?!-_()'=+/@\"";
+
+// Characters sharing the morse code of another character.
+const ALIASES: [(char, char); 9] = [
+    ('À', 'à'),
+    ('À', 'Å'),
+    ('À', 'å'),
+    ('Ä', 'ä'),
+    ('È', 'è'),
+    ('É', 'é'),
+    ('Ö', 'ö'),
+    ('Ü', 'ü'),
+    ('Ñ', 'ñ'),
+];
+
+/// Reader providing bytes from a slice, replacing user space buffers.
+struct SliceReader<'a> {
+    data: &'a [u8], // Bytes not read yet
+}
+
+impl IoBufferReader for SliceReader<'_> {
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    fn skip(&mut self, count: usize) -> Result {
+        if count > self.data.len() {
+            return Err(EFAULT);
+        }
+        self.data = &self.data[count..];
+        Ok(())
+    }
+
+    unsafe fn read_raw(&mut self, out: *mut u8, len: usize) -> Result {
+        if len > self.data.len() {
+            return Err(EFAULT);
+        }
+
+        // SAFETY: The caller guarantees out is valid for len bytes. data contains len bytes.
+        unsafe { ptr::copy_nonoverlapping(self.data.as_ptr(), out, len) };
+        self.data = &self.data[len..];
+        Ok(())
+    }
+}
+
+/// Read a single character from a UTF-8 byte sequence via try_read_char.
+fn read_char(bytes: &[u8]) -> Option<Result<char, DecodeError>> {
+    read_char_in(Encoding::Utf8, bytes)
+}
+
+/// Read a single character from a byte sequence in the given encoding via try_read_char.
+fn read_char_in(encoding: Encoding, bytes: &[u8]) -> Option<Result<char, DecodeError>> {
+    let mut decoder = Decoder::new(encoding, None);
+    try_read_char(&mut decoder, &mut SliceReader { data: bytes }).map(|seq| seq.char)
+}
+
+#[no_mangle]
+extern "C" fn text_to_morse_test_morse_code_table(test: *mut Kunit) {
+    for char in SUPPORTED.chars() {
+        let code = morse_code_from(char);
+        check!(test, code != ERROR_CODE);
+        check!(test, code.len() <= MAX_MORSE_CODE_LEN);
+        check!(test, code.ends_with(' '));
+        check!(test, code.trim_end().bytes().all(|byte| SYMBOLS.as_bytes().contains(&byte)));
+        check!(test, morse_code_from(char.to_ascii_lowercase()) == code);
+    }
+    for (char, alias) in ALIASES {
+        check!(test, morse_code_from(alias) == morse_code_from(char));
+    }
+    for char in [' ', '\n', '\r', '\t', '\0'] {
+        check!(test, morse_code_from(char).trim_matches(char).is_empty());
+    }
+    for char in ['#', '<', '€', 'ÿ', '\u{1F600}'] {
+        check!(test, morse_code_from(char) == ERROR_CODE);
+    }
+}
+
+#[no_mangle]
+extern "C" fn text_to_morse_test_morse_code_unique(test: *mut Kunit) {
+    for (index, char) in SUPPORTED.chars().enumerate() {
+        for other in SUPPORTED.chars().skip(index + 1) {
+            check!(test, morse_code_from(char) != morse_code_from(other));
+        }
+    }
+}
+
+#[no_mangle]
+extern "C" fn text_to_morse_test_ringbuffer_empty(test: *mut Kunit) {
+    let mut queue = Ringbuffer::<u8, 4>::new();
+    check!(test, queue.is_empty());
+    check!(test, queue.len() == 0);
+    check!(test, queue.free() == 4);
+    check!(test, queue.peek().is_none());
+    check!(test, queue.try_pop() == Err(Error::Empty));
+
+    check!(test, queue.try_push(1).is_ok());
+    check!(test, queue.try_pop() == Ok(1));
+    check!(test, queue.is_empty());
+    check!(test, queue.try_pop() == Err(Error::Empty));
+}
+
+#[no_mangle]
+extern "C" fn text_to_morse_test_ringbuffer_full(test: *mut Kunit) {
+    let mut queue = Ringbuffer::<u8, 4>::new();
+    for byte in 0..4 {
+        check!(test, queue.try_push(byte).is_ok());
+    }
+    check!(test, queue.is_full());
+    check!(test, queue.free() == 0);
+    check!(test, queue.try_push(4) == Err(Error::Full));
+    check!(test, queue.len() == 4);
+
+    // Overwriting evicts the oldest value.
+    check!(test, queue.push_overwrite(4) == Some(0));
+    check!(test, queue.iter().copied().eq([1, 2, 3, 4]));
+}
+
+#[no_mangle]
+extern "C" fn text_to_morse_test_ringbuffer_wraparound(test: *mut Kunit) {
+    let mut queue = Ringbuffer::<u8, 4>::new();
+    for byte in 0..3 {
+        check!(test, queue.try_push(byte).is_ok());
+    }
+    check!(test, queue.try_pop() == Ok(0));
+    check!(test, queue.try_pop() == Ok(1));
+
+    // Write position wraps around after the last slot.
+    for byte in 3..6 {
+        check!(test, queue.try_push(byte).is_ok());
+    }
+    check!(test, queue.write_pos() == 2);
+    check!(test, queue.read_pos() == 2);
+    check!(test, queue.is_full());
+    check!(test, queue.get(0) == Some(&2));
+    check!(test, queue.get(3) == Some(&5));
+    check!(test, queue.get(4).is_none());
+
+    // Read position wraps around after the last slot.
+    for byte in 2..6 {
+        check!(test, queue.try_pop() == Ok(byte));
+    }
+    check!(test, queue.read_pos() == 2);
+    check!(test, queue.is_empty());
+}
+
+#[no_mangle]
+extern "C" fn text_to_morse_test_read_char_valid(test: *mut Kunit) {
+    check!(test, read_char(b"a") == Some(Ok('a')));
+    check!(test, read_char("ß".as_bytes()) == Some(Ok('ß')));
+    check!(test, read_char("€".as_bytes()) == Some(Ok('€')));
+    check!(test, read_char("\u{1F600}".as_bytes()) == Some(Ok('\u{1F600}')));
+    check!(test, read_char(&[0x7f]) == Some(Ok('\u{7f}')));
+    check!(test, read_char(&[0xf4, 0x8f, 0xbf, 0xbf]) == Some(Ok('\u{10ffff}')));
+
+    // Only the first character is read.
+    let mut reader = SliceReader { data: "äb".as_bytes() };
+    let sequence = try_read_char(&mut Decoder::new(Encoding::Utf8, None), &mut reader);
+    check!(test, sequence.map(|seq| (seq.char, seq.len)) == Some((Ok('ä'), 2)));
+    check!(test, reader.len() == 1);
+
+    // Continuation bytes and bytes never used in UTF-8 can't start a character.
+    check!(test, read_char(&[0x80]) == Some(Err(DecodeError::Invalid)));
+    check!(test, read_char(&[0xbf]) == Some(Err(DecodeError::Invalid)));
+    check!(test, read_char(&[0xf8, 0x80, 0x80, 0x80]) == Some(Err(DecodeError::Invalid)));
+    check!(test, read_char(&[0xff]) == Some(Err(DecodeError::Invalid)));
+
+    // Code points above U+10FFFF are rejected.
+    check!(test, read_char(&[0xf4, 0x90, 0x80, 0x80]) == Some(Err(DecodeError::Invalid)));
+}
+
+#[no_mangle]
+extern "C" fn text_to_morse_test_read_char_overlong(test: *mut Kunit) {
+    check!(test, read_char(&[0xc0, 0xaf]) == Some(Err(DecodeError::Invalid)));
+    check!(test, read_char(&[0xc1, 0xbf]) == Some(Err(DecodeError::Invalid)));
+    check!(test, read_char(&[0xe0, 0x80, 0xaf]) == Some(Err(DecodeError::Invalid)));
+    check!(test, read_char(&[0xe0, 0x9f, 0xbf]) == Some(Err(DecodeError::Invalid)));
+    check!(test, read_char(&[0xf0, 0x80, 0x80, 0xaf]) == Some(Err(DecodeError::Invalid)));
+    check!(test, read_char(&[0xf0, 0x8f, 0xbf, 0xbf]) == Some(Err(DecodeError::Invalid)));
+}
+
+#[no_mangle]
+extern "C" fn text_to_morse_test_read_char_truncated(test: *mut Kunit) {
+    check!(test, read_char(&[]).is_none());
+    check!(test, read_char(&[0xc3]) == Some(Err(DecodeError::Truncated)));
+    check!(test, read_char(&[0xe2, 0x82]) == Some(Err(DecodeError::Truncated)));
+    check!(test, read_char(&[0xf0, 0x9f, 0x98]) == Some(Err(DecodeError::Truncated)));
+
+    // A new character starts before the sequence is complete.
+    check!(test, read_char(&[0xc3, 0x41]) == Some(Err(DecodeError::Invalid)));
+    check!(test, read_char(&[0xe2, 0x41, 0x41]) == Some(Err(DecodeError::Invalid)));
+
+    // The new character is read next.
+    let mut decoder = Decoder::new(Encoding::Utf8, None);
+    let mut reader = SliceReader {
+        data: &[0xe2, 0x82, 0x41],
+    };
+    let sequence = try_read_char(&mut decoder, &mut reader);
+    check!(test, sequence.map(|seq| seq.len) == Some(2));
+    let sequence = try_read_char(&mut decoder, &mut reader);
+    check!(test, sequence.map(|seq| seq.char) == Some(Ok('A')));
+    check!(test, try_read_char(&mut decoder, &mut reader).is_none());
+}
+
+#[no_mangle]
+extern "C" fn text_to_morse_test_read_char_surrogate(test: *mut Kunit) {
+    // U+D800, U+DBFF, U+DC00 and U+DFFF encoded as 3 byte sequences.
+    check!(test, read_char(&[0xed, 0xa0, 0x80]) == Some(Err(DecodeError::Invalid)));
+    check!(test, read_char(&[0xed, 0xaf, 0xbf]) == Some(Err(DecodeError::Invalid)));
+    check!(test, read_char(&[0xed, 0xb0, 0x80]) == Some(Err(DecodeError::Invalid)));
+    check!(test, read_char(&[0xed, 0xbf, 0xbf]) == Some(Err(DecodeError::Invalid)));
+
+    // Surrogate pair of U+1F600 in CESU-8.
+    let cesu8 = [0xed, 0xa0, 0xbd, 0xed, 0xb8, 0x80];
+    check!(test, read_char(&cesu8) == Some(Err(DecodeError::Invalid)));
+
+    // Code point just below the surrogates is valid.
+    check!(test, read_char(&[0xed, 0x9f, 0xbf]) == Some(Ok('\u{d7ff}')));
+}
+
+#[no_mangle]
+extern "C" fn text_to_morse_test_read_char_encodings(test: *mut Kunit) {
+    // Latin-1 maps each byte to the code point of the same value.
+    check!(test, read_char_in(Encoding::Latin1, &[0xe4]) == Some(Ok('ä')));
+    check!(test, read_char_in(Encoding::Latin1, &[0xff]) == Some(Ok('ÿ')));
+
+    // ASCII rejects each byte above 0x7f.
+    check!(test, read_char_in(Encoding::Ascii, b"a") == Some(Ok('a')));
+    check!(test, read_char_in(Encoding::Ascii, &[0x80]) == Some(Err(DecodeError::Invalid)));
+    check!(test, read_char_in(Encoding::Ascii, &[0xc3, 0xa4]) == Some(Err(DecodeError::Invalid)));
+
+    // UTF-16 is little endian, unless it starts with a big endian BOM.
+    check!(test, read_char_in(Encoding::Utf16, &[0x41, 0x00]) == Some(Ok('A')));
+    check!(test, read_char_in(Encoding::Utf16, &[0x41]) == Some(Err(DecodeError::Truncated)));
+    check!(test, read_char_in(Encoding::Utf16, &[0x00, 0xdc]) == Some(Err(DecodeError::Invalid)));
+    let emoji = [0x3d, 0xd8, 0x00, 0xde];
+    check!(test, read_char_in(Encoding::Utf16, &emoji) == Some(Ok('\u{1F600}')));
+
+    let mut decoder = Decoder::new(Encoding::Utf16, None);
+    let mut reader = SliceReader {
+        data: &[0xfe, 0xff, 0x00, 0x41],
+    };
+    let sequence = try_read_char(&mut decoder, &mut reader);
+    check!(test, sequence.map(|seq| (seq.char, seq.len)) == Some((Ok('\u{feff}'), 2)));
+    let sequence = try_read_char(&mut decoder, &mut reader);
+    check!(test, sequence.map(|seq| seq.char) == Some(Ok('A')));
+    check!(test, decoder.byte_order() == Some(ByteOrder::Big));
+}
@@ -0,0 +1,659 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+//! Line discipline N_MORSE converting the traffic of existing ttys.
+//!
+//! Attached to a tty, e.g. via `ldattach 29 /dev/ttyS0`, text written into the tty is
+//! converted into morse code before it is passed to the driver, and morse code received by
+//! the driver is converted back into text for readers. So applications talk text, while
+//! the serial line carries morse code. The line discipline is registered under the number
+//! given by module parameter LDISC, N_DEVELOPMENT (29) by default.
+//!
+//! Written text is handled like on the ttyMORSE ports, see tty.rs. Received morse code is
+//! expected in the format of the written morse code, see receive.rs. Received text is
+//! delivered as soon as its letters are complete. Termios settings like canonical mode and
+//! echo have no effect on it. While the text buffer of RECEIVE_BUFFER_SIZE bytes is full,
+//! received morse code is left in the buffer of the tty, until readers make room again. So
+//! no text is lost, the driver stops receiving instead. Received cut numbers are expanded back into digits, if enabled via
+//! IOCTL_SET_EXPAND_CUT_NUMBERS.
+
+use super::{
+    ioctl::{FIONREAD, IOCTL_GET_EXPAND_CUT_NUMBERS, IOCTL_SET_EXPAND_CUT_NUMBERS},
+    morse::MAX_MORSE_CODE_LEN,
+    normalize::CutNumbers,
+    receive::Receiver,
+    ringbuffer::Ringbuffer,
+    tty::Converter,
+    CHUNK_SIZE,
+};
+use alloc::vec::Vec;
+use core::{
+    ffi::{c_char, c_int, c_long, c_uint, c_ulong, c_void},
+    mem::{size_of, MaybeUninit},
+    ptr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use kernel::{
+    bindings, c_str,
+    file::flags::O_NONBLOCK,
+    io_buffer::{IoBufferReader, IoBufferWriter},
+    prelude::*,
+    sync::smutex::Mutex,
+    task::Task,
+    user_ptr::UserSlicePtr,
+};
+
+// Number of bytes of received text buffered until it is read.
+const RECEIVE_BUFFER_SIZE: usize = 1024;
+
+// Maximum number of bytes of text received for a byte of morse code: A finished letter of
+// up to 3 bytes, e.g. U+FFFD, and a control character finishing it.
+const MAX_RECEIVED_LEN: usize = 4;
+
+/// Line discipline of the module, unregistered on drop.
+pub(crate) struct LineDiscipline {
+    ops: Box<bindings::tty_ldisc_ops>, // Operations, referenced by the tty core
+}
+
+// SAFETY: The operations are only modified on creation. The tty core only reads them.
+unsafe impl Send for LineDiscipline {}
+
+// SAFETY: LineDiscipline offers no methods accessing the operations.
+unsafe impl Sync for LineDiscipline {}
+
+/// State of the line discipline on a tty.
+struct Line {
+    output: Mutex<Output>, // Written text, converted into morse code
+    input: Mutex<Input>,   // Received morse code and text
+    hung_up: AtomicBool,   // Flag if the tty was hung up
+}
+
+/// Morse code of written text, passed to the driver.
+struct Output {
+    converter: Converter, // Conversion state of the written text
+    morse_code: Vec<u8>,  // Morse code of the last conversion, CHUNK_SIZE bytes of capacity
+    sent: usize,          // Number of bytes of morse_code accepted by the driver
+}
+
+/// Buffer of received text.
+type Text = Ringbuffer<u8, RECEIVE_BUFFER_SIZE>;
+
+/// Received text, waiting to be read.
+struct Input {
+    receiver: Receiver, // Conversion state of received morse code
+    text: Text,         // UTF-8 encoded text, not read yet
+    stopped: bool,      // Flag if receive_buf2 left morse code in the buffer of the tty
+}
+
+impl LineDiscipline {
+    /// Try to register the line discipline.
+    ///
+    /// # Arguments:
+    /// * module: The module owning the line discipline.
+    /// * num: The number of the line discipline, as given to ldattach.
+    ///
+    /// # Returns:
+    /// On success, an Ok containing the registered line discipline,
+    /// on failure an Err containing one of the following error codes:
+    /// * ENOMEM: The operations must be allocated and this fails.
+    /// * EINVAL: num is not a valid line discipline number.
+    /// * EBUSY: Another line discipline is registered under num.
+    pub(crate) fn try_new(module: &'static ThisModule, num: c_int) -> Result<Self> {
+        // SAFETY: tty_ldisc_ops is a plain C struct of function pointers. All zeroes is valid.
+        let mut ops: Box<bindings::tty_ldisc_ops> =
+            Box::try_new(unsafe { MaybeUninit::zeroed().assume_init() })?;
+        ops.num = num;
+        ops.name = c_str!("n_morse").as_char_ptr();
+        ops.owner = module.as_ptr();
+        ops.open = Some(open);
+        ops.close = Some(close);
+        ops.flush_buffer = Some(flush_buffer);
+        ops.read = Some(read);
+        ops.write = Some(write);
+        ops.ioctl = Some(ioctl);
+        ops.poll = Some(poll);
+        ops.hangup = Some(hangup);
+        ops.receive_buf2 = Some(receive_buf2);
+
+        // SAFETY: The operations are allocated on the heap, so they never move. They are
+        // unregistered on drop. The tty core holds a reference to the module while the line
+        // discipline is attached.
+        let ret = unsafe { bindings::tty_register_ldisc(&mut *ops) };
+        if ret != 0 {
+            return Err(Error::from_kernel_errno(ret));
+        }
+        log_info!("Registered line discipline N_MORSE as {}.\n", num);
+        Ok(Self { ops })
+    }
+}
+
+impl Drop for LineDiscipline {
+    fn drop(&mut self) {
+        // SAFETY: The operations were registered on creation. An attached line discipline
+        // holds a reference to the module, so it is not attached to any tty anymore.
+        unsafe { bindings::tty_unregister_ldisc(&mut *self.ops) };
+    }
+}
+
+impl Line {
+    /// Try to create the state of the line discipline on a tty
+    ///
+    /// # Returns:
+    /// On success, an Ok containing the new state,
+    /// on failure an Err containing return code ENOMEM.
+    fn try_new() -> Result<Box<Self>> {
+        let output = Output {
+            converter: Converter::default(),
+            morse_code: Vec::try_with_capacity(CHUNK_SIZE)?,
+            sent: 0,
+        };
+        Ok(Box::try_new(Line {
+            output: Mutex::new(output),
+            input: Mutex::new(Input {
+                receiver: Receiver::new(),
+                text: Ringbuffer::new(),
+                stopped: false,
+            }),
+            hung_up: AtomicBool::new(false),
+        })?)
+    }
+
+    /// Get the state of the line discipline on a tty
+    ///
+    /// # Safety
+    /// The line discipline must be opened on tty and not closed yet.
+    unsafe fn of<'a>(tty: *mut bindings::tty_struct) -> &'a Line {
+        // SAFETY: Guaranteed by the caller. open stored the state in disc_data.
+        unsafe { &*((*tty).disc_data as *const Line) }
+    }
+}
+
+impl Output {
+    /// Get the number of bytes of morse code the driver did not accept yet.
+    fn unsent(&self) -> usize {
+        self.morse_code.len() - self.sent
+    }
+}
+
+/// Wait until a condition of a tty is fulfilled
+///
+/// # Arguments:
+/// * queue: The wait queue of the tty, woken up on changes of the condition.
+/// * nonblocking: Flag if the file does not block.
+/// * ready: Function checking the condition.
+///
+/// # Returns:
+/// An Ok if the condition is fulfilled, otherwise an Err containing one of the following
+/// error codes:
+/// * EAGAIN: The condition is not fulfilled and the file does not block.
+/// * ERESTARTSYS: A signal was received while waiting.
+///
+/// # Safety
+/// queue must be a wait queue of a tty, the line discipline is opened on.
+unsafe fn wait_until(
+    queue: *mut bindings::wait_queue_head,
+    nonblocking: bool,
+    mut ready: impl FnMut() -> bool,
+) -> Result {
+    if ready() {
+        return Ok(());
+    }
+    if nonblocking {
+        return Err(EAGAIN);
+    }
+
+    // Initialized like DEFINE_WAIT_FUNC(wait, woken_wake_function) does. wait never moves
+    // while it is added to queue.
+    // SAFETY: wait_queue_entry is a plain C struct. All zeroes is valid.
+    let mut wait: bindings::wait_queue_entry = unsafe { MaybeUninit::zeroed().assume_init() };
+    // SAFETY: Only returns the current task.
+    wait.private = unsafe { bindings::get_current() } as *mut c_void;
+    wait.func = Some(bindings::woken_wake_function);
+    wait.entry.next = &mut wait.entry;
+    wait.entry.prev = &mut wait.entry;
+
+    // SAFETY: queue is valid, guaranteed by the caller. wait is removed before it is dropped.
+    unsafe { bindings::add_wait_queue(queue, &mut wait) };
+    let result = loop {
+        if ready() {
+            break Ok(());
+        }
+        if Task::current().signal_pending() {
+            break Err(ERESTARTSYS);
+        }
+        // SAFETY: wait is added to queue. Sleeps until it is woken up or a signal arrives.
+        unsafe {
+            bindings::wait_woken(
+                &mut wait,
+                bindings::TASK_INTERRUPTIBLE as c_uint,
+                c_long::MAX,
+            )
+        };
+    };
+    // SAFETY: wait was added to queue above.
+    unsafe { bindings::remove_wait_queue(queue, &mut wait) };
+    result
+}
+
+/// Get the number of bytes the driver of a tty accepts
+///
+/// # Safety
+/// tty must be a tty, the line discipline is opened on.
+unsafe fn write_room(tty: *mut bindings::tty_struct) -> usize {
+    // SAFETY: Guaranteed by the caller.
+    unsafe { bindings::tty_write_room(tty) as usize }
+}
+
+/// Check if a file does not block
+///
+/// # Safety
+/// file must be an opened file.
+unsafe fn is_nonblocking(file: *mut bindings::file) -> bool {
+    // SAFETY: Guaranteed by the caller.
+    unsafe { (*file).f_flags & O_NONBLOCK != 0 }
+}
+
+/// Attach the line discipline to a tty
+///
+/// # Arguments:
+/// * tty: The tty to attach to.
+///
+/// # Returns:
+/// 0 on success, otherwise a negative error code: ENOMEM if the state can't be allocated.
+unsafe extern "C" fn open(tty: *mut bindings::tty_struct) -> c_int {
+    let line = match Line::try_new() {
+        Ok(line) => line,
+        Err(errno) => return errno.to_kernel_errno(),
+    };
+
+    // SAFETY: The tty core passes a valid tty. The state is released by close.
+    unsafe { (*tty).disc_data = Box::into_raw(line) as *mut c_void };
+    0
+}
+
+/// Detach the line discipline from a tty
+///
+/// # Arguments:
+/// * tty: The tty to detach from.
+unsafe extern "C" fn close(tty: *mut bindings::tty_struct) {
+    // SAFETY: The state was stored by open. The tty core calls no other operation anymore.
+    unsafe {
+        drop(Box::from_raw((*tty).disc_data as *mut Line));
+        (*tty).disc_data = ptr::null_mut();
+    }
+}
+
+/// Drop all received text, which was not read yet. The expansion of cut numbers is kept.
+///
+/// # Arguments:
+/// * tty: The tty to flush.
+///
+/// # Notes:
+/// The tty core drops the morse code left in the buffer of the tty before, so receiving
+/// needs no restart.
+unsafe extern "C" fn flush_buffer(tty: *mut bindings::tty_struct) {
+    // SAFETY: The tty core only calls operations of an opened line discipline.
+    let line = unsafe { Line::of(tty) };
+    let mut input = line.input.lock();
+    input.receiver.reset();
+    input.text.clear();
+    input.stopped = false;
+}
+
+/// Restart passing received morse code to receive_buf2, after it left some in the buffer of
+/// a tty
+///
+/// # Safety
+/// tty must be a tty, the line discipline is opened on. The input lock must not be held,
+/// since the tty core holds the buffer lock of the tty while calling receive_buf2.
+unsafe fn restart_receiving(tty: *mut bindings::tty_struct) {
+    // SAFETY: Guaranteed by the caller. Unlocking the buffer of a port queues the work
+    // passing its contents to the line discipline, if morse code is left.
+    unsafe {
+        let port = (*tty).port;
+        if !port.is_null() {
+            bindings::tty_buffer_lock_exclusive(port);
+            bindings::tty_buffer_unlock_exclusive(port);
+        }
+    }
+}
+
+/// Read received text
+///
+/// # Arguments:
+/// * tty: The tty to read from.
+/// * file: The file read from.
+/// * buf: Kernel buffer to store the text in.
+/// * nr: The size of buf.
+///
+/// # Returns:
+/// The number of bytes read on success, 0 if the tty was hung up. Otherwise a negative error
+/// code of wait_until. Blocks until text was received, unless the file does not block.
+///
+/// # Notes:
+/// Receiving is restarted, if receive_buf2 left morse code in the buffer of the tty.
+unsafe extern "C" fn read(
+    tty: *mut bindings::tty_struct,
+    file: *mut bindings::file,
+    buf: *mut u8,
+    nr: usize,
+    _cookie: *mut *mut c_void,
+    _offset: c_ulong,
+) -> isize {
+    // SAFETY: The tty core only calls operations of an opened line discipline.
+    let line = unsafe { Line::of(tty) };
+    // SAFETY: tty and file are valid for the call. read_wait is woken up by receive_buf2.
+    let ready = unsafe {
+        wait_until(&mut (*tty).read_wait, is_nonblocking(file), || {
+            line.hung_up.load(Ordering::Relaxed) || !line.input.lock().text.is_empty()
+        })
+    };
+    if let Err(errno) = ready {
+        return errno.to_kernel_errno() as isize;
+    }
+
+    // SAFETY: The tty core passes a kernel buffer of nr bytes.
+    let buf = unsafe { core::slice::from_raw_parts_mut(buf, nr) };
+    let mut input = line.input.lock();
+    let mut total_bytes_read = 0;
+    for dst in buf.iter_mut() {
+        match input.text.try_pop() {
+            Ok(byte) => *dst = byte,
+            Err(_) => break,
+        }
+        total_bytes_read += 1;
+    }
+    let restart = input.stopped && MAX_RECEIVED_LEN <= input.text.free();
+    if restart {
+        input.stopped = false;
+    }
+    drop(input);
+
+    if restart {
+        // SAFETY: tty is valid for the call. The input lock was dropped.
+        unsafe { restart_receiving(tty) };
+    }
+    total_bytes_read as isize
+}
+
+/// Convert text and write its morse code into a tty
+///
+/// # Arguments:
+/// * tty: The tty to write into.
+/// * file: The file written into.
+/// * buf: The UTF-8 encoded text.
+/// * nr: The number of bytes in buf.
+///
+/// # Returns:
+/// The number of bytes written on success. Otherwise a negative error code of
+/// Converter::convert, wait_until or the driver, or EIO if the tty was hung up. Blocks until
+/// the driver accepted all morse code, unless the file does not block.
+///
+/// # Notes:
+/// Morse code the driver did not accept by a previous write is passed on first.
+/// Phrases are only compressed, if they fit into the write room of the driver. Otherwise
+/// they are converted uncompressed.
+unsafe extern "C" fn write(
+    tty: *mut bindings::tty_struct,
+    file: *mut bindings::file,
+    buf: *const u8,
+    nr: usize,
+) -> isize {
+    // SAFETY: The tty core only calls operations of an opened line discipline.
+    let line = unsafe { Line::of(tty) };
+    // SAFETY: The tty core passes a kernel buffer containing nr bytes.
+    let bytes = unsafe { core::slice::from_raw_parts(buf, nr) };
+    // SAFETY: file is valid for the call.
+    let nonblocking = unsafe { is_nonblocking(file) };
+
+    let mut total_bytes_written = 0;
+    loop {
+        let unsent = line.output.lock().unsent();
+        if unsent == 0 && total_bytes_written == bytes.len() {
+            break;
+        }
+        // Unsent morse code is passed on in parts, new text needs room for a letter.
+        let needed_room = if unsent > 0 { 1 } else { MAX_MORSE_CODE_LEN };
+
+        // SAFETY: tty is valid for the call. write_wait is woken up by tty_wakeup.
+        let ready = unsafe {
+            wait_until(&mut (*tty).write_wait, nonblocking, || {
+                line.hung_up.load(Ordering::Relaxed) || needed_room <= write_room(tty)
+            })
+        };
+        let result = match ready {
+            Ok(()) if line.hung_up.load(Ordering::Relaxed) => Err(EIO),
+            // SAFETY: tty is valid for the call.
+            Ok(()) => unsafe { write_chunk(tty, line, &bytes[total_bytes_written..]) },
+            Err(errno) => Err(errno),
+        };
+        match result {
+            Ok(bytes_written) => total_bytes_written += bytes_written,
+            Err(errno) if total_bytes_written == 0 => return errno.to_kernel_errno() as isize,
+            Err(_) => break,
+        }
+    }
+    total_bytes_written as isize
+}
+
+/// Pass unsent morse code to the driver of a tty, or convert as much text as it accepts
+///
+/// # Arguments:
+/// * tty: The tty to write into.
+/// * line: The state of the line discipline on tty.
+/// * bytes: The UTF-8 encoded text.
+///
+/// # Returns:
+/// On success, an Ok containing the number of bytes of text converted, 0 if unsent morse
+/// code was passed on instead. On failure, an Err containing one of the error codes of
+/// Converter::convert, or the error code of the driver.
+///
+/// # Notes:
+/// The driver may accept less morse code than its write room. The rest is kept and passed
+/// on by the next call. So is all morse code, if the driver fails after text was converted.
+///
+/// # Safety
+/// tty must be a tty, the line discipline is opened on.
+unsafe fn write_chunk(tty: *mut bindings::tty_struct, line: &Line, bytes: &[u8]) -> Result<usize> {
+    let mut output = line.output.lock();
+    let mut bytes_written = 0;
+    if output.unsent() == 0 {
+        // SAFETY: Guaranteed by the caller.
+        let space = unsafe { write_room(tty) }.min(CHUNK_SIZE);
+        let Output {
+            converter,
+            morse_code,
+            sent,
+        } = &mut *output;
+        morse_code.clear();
+        *sent = 0;
+        bytes_written = converter.convert(bytes, space, morse_code)?;
+    }
+
+    let unsent = &output.morse_code[output.sent..];
+    if unsent.is_empty() {
+        return Ok(bytes_written);
+    }
+    // SAFETY: Every tty driver implements write. unsent is valid for its length.
+    let ret = unsafe {
+        let driver_write = (*(*tty).ops).write.ok_or(EIO)?;
+        driver_write(tty, unsent.as_ptr(), unsent.len() as c_int)
+    };
+    if ret >= 0 {
+        output.sent += ret as usize;
+    } else if bytes_written == 0 {
+        return Err(Error::from_kernel_errno(ret));
+    }
+    Ok(bytes_written)
+}
+
+/// Handle ioctl commands of a tty
+///
+/// # Arguments:
+/// * tty: The tty the command is issued on.
+/// * cmd: The ioctl command to handle. FIONREAD returns the number of bytes of received
+///   text, IOCTL_GET_EXPAND_CUT_NUMBERS and IOCTL_SET_EXPAND_CUT_NUMBERS get and set the
+///   expansion of received cut numbers. All other commands are handled like by n_tty,
+///   e.g. termios settings.
+/// * arg: The raw argument of the command.
+///
+/// # Returns:
+/// 0 on success, otherwise a negative error code: EFAULT if the argument can't be
+/// transferred, or the error code of n_tty_ioctl_helper.
+unsafe extern "C" fn ioctl(tty: *mut bindings::tty_struct, cmd: c_uint, arg: c_ulong) -> c_int {
+    // SAFETY: The tty core only calls operations of an opened line discipline.
+    let line = unsafe { Line::of(tty) };
+    // SAFETY: arg is the user space pointer given to commands with an int or u32 argument.
+    // It is accessed only once and all accesses are checked by UserSlicePtr.
+    let user_ptr = || unsafe { UserSlicePtr::new(arg as *mut c_void, size_of::<u32>()) };
+    let result = match cmd {
+        FIONREAD => {
+            let available = line.input.lock().text.len();
+            user_ptr().writer().write(&(available as i32))
+        }
+        IOCTL_GET_EXPAND_CUT_NUMBERS => {
+            let expand = line.input.lock().receiver.expansion().is_some();
+            user_ptr().writer().write(&(expand as u32))
+        }
+        IOCTL_SET_EXPAND_CUT_NUMBERS => user_ptr().reader().read::<u32>().map(|expand| {
+            let expansion = (expand != 0).then(CutNumbers::default);
+            line.input.lock().receiver.set_expansion(expansion);
+        }),
+        // SAFETY: Arguments are passed on as given by the tty core.
+        _ => return unsafe { bindings::n_tty_ioctl_helper(tty, cmd, arg) },
+    };
+    match result {
+        Ok(()) => 0,
+        Err(errno) => errno.to_kernel_errno(),
+    }
+}
+
+/// Poll a tty for received text and write room
+///
+/// # Arguments:
+/// * tty: The tty to poll.
+/// * file: The polled file.
+/// * wait: The poll table to add the wait queues of the tty to.
+///
+/// # Returns:
+/// The mask of poll events of tty.
+unsafe extern "C" fn poll(
+    tty: *mut bindings::tty_struct,
+    file: *mut bindings::file,
+    wait: *mut bindings::poll_table_struct,
+) -> bindings::__poll_t {
+    // SAFETY: The tty core only calls operations of an opened line discipline.
+    let line = unsafe { Line::of(tty) };
+
+    // Same as poll_wait for both wait queues of tty.
+    // SAFETY: The tty core passes a valid file and a valid or null poll table.
+    unsafe {
+        if let Some(qproc) = wait.as_ref().and_then(|wait| wait._qproc) {
+            qproc(file, &mut (*tty).read_wait, wait);
+            qproc(file, &mut (*tty).write_wait, wait);
+        }
+    }
+
+    // SAFETY: tty is valid for the call.
+    let room = unsafe { write_room(tty) };
+    let mut mask: bindings::__poll_t = 0;
+    if !line.input.lock().text.is_empty() {
+        mask |= bindings::POLLIN | bindings::POLLRDNORM;
+    }
+    if line.hung_up.load(Ordering::Relaxed) {
+        mask |= bindings::POLLHUP;
+    } else if MAX_MORSE_CODE_LEN <= room {
+        mask |= bindings::POLLOUT | bindings::POLLWRNORM;
+    }
+    mask
+}
+
+/// Wake up readers and writers of a hung up tty
+///
+/// # Arguments:
+/// * tty: The hung up tty.
+unsafe extern "C" fn hangup(tty: *mut bindings::tty_struct) {
+    // SAFETY: The tty core only calls operations of an opened line discipline.
+    let line = unsafe { Line::of(tty) };
+    line.hung_up.store(true, Ordering::Relaxed);
+    // SAFETY: Same as wake_up_interruptible on both wait queues of tty.
+    unsafe {
+        let mode = bindings::TASK_INTERRUPTIBLE as c_uint;
+        bindings::__wake_up(&mut (*tty).read_wait, mode, 1, ptr::null_mut());
+        bindings::__wake_up(&mut (*tty).write_wait, mode, 1, ptr::null_mut());
+    }
+}
+
+/// Convert morse code received by the driver of a tty
+///
+/// # Arguments:
+/// * tty: The receiving tty.
+/// * cp: The received bytes.
+/// * fp: The flags of the received bytes, or null if all are normal.
+/// * count: The number of received bytes.
+///
+/// # Returns:
+/// The number of bytes consumed. Bytes are only consumed while the text buffer has room
+/// for their text, the tty core keeps the rest until read restarts receiving.
+///
+/// # Notes:
+/// Bytes flagged with errors, e.g. parity errors, are dropped.
+unsafe extern "C" fn receive_buf2(
+    tty: *mut bindings::tty_struct,
+    cp: *const u8,
+    fp: *const c_char,
+    count: c_int,
+) -> c_int {
+    // SAFETY: The tty core only calls operations of an opened line discipline.
+    let line = unsafe { Line::of(tty) };
+    // SAFETY: The tty core passes count bytes and count flags, if any.
+    let (bytes, flags) = unsafe {
+        let flags = match fp.is_null() {
+            true => None,
+            false => Some(core::slice::from_raw_parts(fp, count as usize)),
+        };
+        (core::slice::from_raw_parts(cp, count as usize), flags)
+    };
+
+    let mut input = line.input.lock();
+    let Input {
+        receiver,
+        text,
+        stopped,
+    } = &mut *input;
+    let mut consumed = 0;
+    for (index, byte) in bytes.iter().enumerate() {
+        if text.free() < MAX_RECEIVED_LEN {
+            *stopped = true;
+            break;
+        }
+        consumed += 1;
+        if flags.map_or(false, |flags| {
+            flags[index] != bindings::TTY_NORMAL as c_char
+        }) {
+            continue;
+        }
+        receiver.push(*byte, |char| {
+            let mut encoded = [0u8; 4];
+            for byte in char.encode_utf8(&mut encoded).as_bytes() {
+                // Can't fail, room for MAX_RECEIVED_LEN bytes was checked.
+                let _ = text.try_push(*byte);
+            }
+        });
+    }
+    let available = !text.is_empty();
+    drop(input);
+
+    if available {
+        // SAFETY: Same as wake_up_interruptible on the read wait queue of tty.
+        unsafe {
+            bindings::__wake_up(
+                &mut (*tty).read_wait,
+                bindings::TASK_INTERRUPTIBLE as c_uint,
+                1,
+                ptr::null_mut(),
+            )
+        };
+    }
+    consumed as c_int
+}
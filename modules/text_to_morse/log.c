@@ -0,0 +1,20 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+/*
+ * Rate limit of the error messages of the module.
+ *
+ * DEFINE_RATELIMIT_STATE initializes the lock of the state statically, which is only
+ * possible in C. The state belongs to the module alone, so messages of other subsystems
+ * don't use up its budget and vice versa. The wrapper is declared in log.rs.
+ */
+
+#include <linux/ratelimit.h>
+
+static DEFINE_RATELIMIT_STATE(text_to_morse_ratelimit, DEFAULT_RATELIMIT_INTERVAL,
+			      DEFAULT_RATELIMIT_BURST);
+
+bool text_to_morse_err_ratelimit(void)
+{
+	return ___ratelimit(&text_to_morse_ratelimit, "text_to_morse");
+}
@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+//! Logging with a runtime configurable log level.
+//!
+//! The initial log level is given by module parameter LOG_LEVEL. Afterwards it can be
+//! changed via /sys/kernel/text_to_morse/log_level. Supported log levels are:
+//! * 0: Log nothing.
+//! * 1: Log errors. Errors are rate-limited, so a misbehaving process can't flood the log.
+//!   The rate limit belongs to the module, see log.c.
+//! * 2: Log errors and informational messages about each syscall.
+//!
+//! Messages logged per character use pr_debug! and are independent of the log level.
+//! They are only compiled in if the kernel is built with Rust debug assertions.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+extern "C" {
+    fn text_to_morse_err_ratelimit() -> bool;
+}
+
+/// Log level to log errors.
+pub(crate) const LOG_ERR: u32 = 1;
+
+/// Log level to log errors and informational messages.
+pub(crate) const LOG_INFO: u32 = 2;
+
+// Currently active log level.
+static LOG_LEVEL: AtomicU32 = AtomicU32::new(LOG_ERR);
+
+/// Get the current log level.
+pub(crate) fn level() -> u32 {
+    LOG_LEVEL.load(Ordering::Relaxed)
+}
+
+/// Set the current log level
+///
+/// # Arguments:
+/// * level: The new log level. Values above LOG_INFO are clamped to LOG_INFO.
+pub(crate) fn set_level(level: u32) {
+    LOG_LEVEL.store(level.min(LOG_INFO), Ordering::Relaxed);
+}
+
+/// Check if an error message shall be logged
+///
+/// # Returns:
+/// true if errors are logged with the current log level and the rate limit of the module
+/// is not exceeded, otherwise false.
+pub(crate) fn err_enabled() -> bool {
+    if level() < LOG_ERR {
+        return false;
+    }
+
+    // SAFETY: The rate limit state is statically initialized in log.c and locks itself.
+    unsafe { text_to_morse_err_ratelimit() }
+}
+
+/// Log an informational message, if enabled by the current log level.
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        if $crate::log::level() >= $crate::log::LOG_INFO {
+            pr_info!($($arg)*);
+        }
+    };
+}
+
+/// Log an error message, if enabled by the current log level and not rate-limited.
+macro_rules! log_err {
+    ($($arg:tt)*) => {
+        if $crate::log::err_enabled() {
+            pr_err!($($arg)*);
+        }
+    };
+}
@@ -0,0 +1,1508 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+
+//! Kernel Module to convert UTF-8 text to morse code.
+//! Author: Simon Brummer <simon.brummer@posteo.de>
+
+#[macro_use]
+mod log;
+
+// The conversion core is shared with the host crate under core/. Its items are public
+// for the host crate, but only used within this crate.
+#[allow(unreachable_pub)]
+#[path = "core/src/ringbuffer.rs"]
+mod ringbuffer;
+use ringbuffer::{Ring, Ringbuffer};
+
+#[allow(unreachable_pub)]
+#[path = "core/src/morse.rs"]
+mod morse;
+use morse::{is_letter_end, Prosign, MAX_MORSE_CODE_LEN, MAX_PROSIGN_LEN};
+
+#[allow(unreachable_pub)]
+#[path = "core/src/utf8.rs"]
+mod utf8;
+use utf8::{DecodeError, Policy, Sequence};
+
+#[allow(unreachable_pub)]
+#[path = "core/src/encoding.rs"]
+mod encoding;
+use encoding::{ByteOrder, Decoder, Encoding};
+
+#[allow(unreachable_pub)]
+#[path = "core/src/normalize.rs"]
+mod normalize;
+use normalize::{CutNumbers, Normalizer, Stages};
+
+#[allow(unreachable_pub)]
+#[path = "core/src/compress.rs"]
+mod compress;
+use compress::{Compressor, Dictionary, DEFAULT_DICTIONARY};
+
+#[allow(unreachable_pub)]
+#[path = "core/src/receive.rs"]
+mod receive;
+
+#[allow(unreachable_pub)]
+#[path = "core/src/convert.rs"]
+mod convert;
+use convert::{convert, Stop};
+
+mod ioctl;
+
+mod sysfs;
+use sysfs::{Sysfs, SysfsDir};
+
+mod fault;
+use fault::Faults;
+
+#[cfg(CONFIG_DEBUG_FS)]
+mod debugfs;
+#[cfg(CONFIG_DEBUG_FS)]
+use debugfs::{Debugfs, DebugfsDir};
+
+mod trace;
+
+mod control;
+use control::Control;
+
+#[cfg(CONFIG_CONFIGFS_FS)]
+mod configfs;
+#[cfg(CONFIG_CONFIGFS_FS)]
+use configfs::Configfs;
+
+#[cfg(CONFIG_TTY)]
+mod tty;
+#[cfg(CONFIG_TTY)]
+use tty::Tty;
+
+#[cfg(CONFIG_TTY)]
+mod ldisc;
+#[cfg(CONFIG_TTY)]
+use ldisc::LineDiscipline;
+
+#[cfg(CONFIG_KUNIT)]
+mod kunit;
+
+use alloc::vec::Vec;
+use core::mem::MaybeUninit;
+use kernel::{
+    file::{
+        self,
+        flags::{O_ACCMODE, O_RDONLY, O_RDWR, O_WRONLY},
+        IoctlCommand,
+    },
+    fmt,
+    io_buffer::{IoBufferReader, IoBufferWriter, ReadableFromBytes, WritableToBytes},
+    miscdev,
+    prelude::*,
+    str::CString,
+    sync::{smutex::Mutex, Arc, CondVar},
+    ForeignOwnable,
+};
+
+// Constants and static data
+const MAX_DEVICES: usize = 16;
+const MAX_TTYS: u32 = 8;
+const BUFFER_SIZE: usize = 256;
+const MAX_QUEUE_SIZE: usize = 65536;
+const DEFAULT_WPM: u32 = 20;
+const MAX_WPM: u32 = 60;
+const CHUNK_SIZE: usize = BUFFER_SIZE - MAX_PROSIGN_LEN;
+
+kernel::init_static_sync! {
+    static READ_CONDITION: CondVar;
+    static WRITE_CONDITION: CondVar;
+}
+
+// Pool of all existing devices.
+static DEVICES_POOL: Mutex<Vec<PoolEntry>> = Mutex::new(Vec::new());
+
+// Serializes creation and removal of devices. Interfaces of devices are added and removed
+// with this lock held instead of the pool lock, since their callbacks lookup devices in
+// the pool and removal of an interface waits until all running callbacks returned.
+static POOL_UPDATE: Mutex<()> = Mutex::new(());
+
+module! {
+    type: Module,
+    name: "text_to_morse",
+    author: "Simon Brummer <simon.brummer@posteo.de>",
+    description: "Module converting text to morse code",
+    license: "Dual MPL/GPL",
+    params: {
+        DEVICES: usize {
+            default: 4,
+            permissions: 0o444,
+            description: "Number of devices to create on load.",
+        },
+        LOG_LEVEL: u32 {
+            default: 1,
+            permissions: 0o444,
+            description: "Initial log level. 0: Nothing, 1: Errors (rate-limited), 2: Info.",
+        },
+        OVERWRITE: bool {
+            default: false,
+            permissions: 0o444,
+            description: "Drop the oldest morse code instead of blocking writers on full devices.",
+        },
+        BROADCAST: bool {
+            default: false,
+            permissions: 0o444,
+            description: "Allow multiple readers per device, each receiving all morse code.",
+        },
+        MULTI_WRITER: bool {
+            default: false,
+            permissions: 0o444,
+            description: "Allow multiple writers per device, each write is stored atomically.",
+        },
+        PRIVATE_SESSIONS: bool {
+            default: false,
+            permissions: 0o444,
+            description: "Give each read/write open of a device a private conversion session.",
+        },
+        UTF8_POLICY: u32 {
+            default: 0,
+            permissions: 0o444,
+            description: "Handling of invalid UTF-8. 0: Fail, 1: Replace with U+FFFD, 2: Skip.",
+        },
+        ENCODING: u32 {
+            default: 0,
+            permissions: 0o444,
+            description: "Encoding of written text. 0: UTF-8, 1: Latin-1, 2: UTF-16, 3: ASCII.",
+        },
+        NORMALIZATION: u32 {
+            default: 0,
+            permissions: 0o444,
+            description: "Bitmask of normalization stages of written text. See ABI/dev-morse.",
+        },
+        TTYS: u32 {
+            default: 1,
+            permissions: 0o444,
+            description: "Number of ttyMORSE ports to register on load. 0 disables them.",
+        },
+        LDISC: i32 {
+            default: 29,
+            permissions: 0o444,
+            description: "Number of line discipline N_MORSE, see ldattach(8). 0 disables it.",
+        },
+    },
+}
+
+/// Core kernel module containing all module data.
+struct Module {
+    // Control device registration object.
+    _control: Pin<Box<miscdev::Registration<Control>>>,
+    // configfs subsystem declaring devices.
+    #[cfg(CONFIG_CONFIGFS_FS)]
+    _configfs: Configfs,
+    // TTY driver of the ttyMORSE ports, if any.
+    #[cfg(CONFIG_TTY)]
+    _tty: Option<Tty>,
+    // Line discipline N_MORSE, if enabled.
+    #[cfg(CONFIG_TTY)]
+    _ldisc: Option<LineDiscipline>,
+    // Module wide interfaces.
+    context: Arc<Context>,
+}
+
+/// Module wide interfaces, the interfaces of each device are added to.
+struct Context {
+    sysfs: Sysfs, // sysfs directory of the module
+    #[cfg(CONFIG_DEBUG_FS)]
+    debugfs: Debugfs, // debugfs directory of the module
+}
+
+impl kernel::Module for Module {
+    fn init(_name: &'static CStr, module: &'static ThisModule) -> Result<Self> {
+        log::set_level(*LOG_LEVEL.read());
+        log_info!("Loading module text_to_morse.\n");
+
+        // Create requested number of devices. If too much devices
+        // shall be created fail on loading with EOVERFLOW.
+        let devices = DEVICES.read().clone();
+        if MAX_DEVICES < devices {
+            pr_crit!(
+                "Error: Unable to create more devices than {}. \
+                 Change parameter DEVICES accordingly.\n",
+                MAX_DEVICES
+            );
+            return Err(EOVERFLOW);
+        }
+
+        if Policy::from_id(*UTF8_POLICY.read()).is_none() {
+            pr_crit!("Error: Unknown UTF-8 policy. Change parameter UTF8_POLICY accordingly.\n");
+            return Err(EINVAL);
+        }
+
+        if Encoding::from_id(*ENCODING.read()).is_none() {
+            pr_crit!("Error: Unknown encoding. Change parameter ENCODING accordingly.\n");
+            return Err(EINVAL);
+        }
+
+        if Stages::from_bits(*NORMALIZATION.read()).is_none() {
+            pr_crit!(
+                "Error: Unknown normalization stages. \
+                 Change parameter NORMALIZATION accordingly.\n"
+            );
+            return Err(EINVAL);
+        }
+
+        if MAX_TTYS < *TTYS.read() {
+            pr_crit!(
+                "Error: Unable to register more ttyMORSE ports than {}. \
+                 Change parameter TTYS accordingly.\n",
+                MAX_TTYS
+            );
+            return Err(EOVERFLOW);
+        }
+
+        // 0 is N_TTY, which can't be replaced, so it disables N_MORSE.
+        if !(0..kernel::bindings::NR_LDISCS as i32).contains(&*LDISC.read()) {
+            pr_crit!(
+                "Error: Invalid line discipline number. Change parameter LDISC accordingly.\n"
+            );
+            return Err(EINVAL);
+        }
+
+        let context = Arc::try_new(Context {
+            sysfs: Sysfs::try_new(module)?,
+            #[cfg(CONFIG_DEBUG_FS)]
+            debugfs: Debugfs::try_new(module)?,
+        })?;
+
+        let control =
+            miscdev::Registration::new_pinned(fmt!("text_to_morse_ctl"), context.clone())?;
+
+        // On failure, module is dropped and removes all created devices.
+        let module = Module {
+            _control: control,
+            #[cfg(CONFIG_CONFIGFS_FS)]
+            _configfs: Configfs::try_new(context.clone(), module)?,
+            #[cfg(CONFIG_TTY)]
+            _tty: match *TTYS.read() {
+                0 => None,
+                ttys => Some(Tty::try_new(module, ttys)?),
+            },
+            #[cfg(CONFIG_TTY)]
+            _ldisc: match *LDISC.read() {
+                0 => None,
+                num => Some(LineDiscipline::try_new(module, num)?),
+            },
+            context,
+        };
+
+        let (config, options) = (DeviceConfig::from_params(), DeviceOptions::default());
+        for _ in 0..devices {
+            let device = Device::try_create(&module.context, &config, &options, None)?;
+            log_info!("Created device {}\n", device.id);
+        }
+        Ok(module)
+    }
+}
+
+impl Drop for Module {
+    fn drop(&mut self) {
+        log_info!("Unloading module text_to_morse.\n");
+        Device::remove_all();
+    }
+}
+
+/// Read access of a Device, tracking the position of a reader in the morse code stream.
+struct Reader {
+    id: u64,                                  // Id of the reader, unique per device.
+    cursor: u64,                              // Stream position of the next byte to read.
+    lagged: u64,                              // Number of bytes dropped before they were read.
+    rest: Ringbuffer<u8, MAX_MORSE_CODE_LEN>, // Unread rest of a dropped, partially read letter.
+}
+
+/// Statistics of a Device. Transferred as is to user space, see IOCTL_GET_STATS.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct Statistics {
+    bytes_in: u64,        // Number of bytes converted into morse code
+    chars_converted: u64, // Number of characters converted into morse code
+    bytes_out: u64,       // Number of bytes of morse code read
+    unknown_chars: u64,   // Number of characters without morse code representation
+    invalid_utf8: u64,    // Number of rejected, replaced or skipped invalid sequences
+    writer_blocks: u64,   // Number of times a writer blocked on a full device
+    reader_blocks: u64,   // Number of times a reader blocked on an empty device
+    interrupts: u64,      // Number of blocked reads and writes interrupted by a signal
+}
+
+// SAFETY: Statistics consists of u64 fields only, so it has no padding and all bytes are
+// initialized.
+unsafe impl WritableToBytes for Statistics {}
+
+/// Settings of a Device. Transferred as is from/to user space, see IOCTL_CREATE_DEVICE.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct DeviceConfig {
+    id: u32,               // Id of the device, set on creation
+    overwrite: u32,        // Overwrite mode, 0 if disabled
+    broadcast: u32,        // Broadcast mode, 0 if disabled
+    multi_writer: u32,     // Multi writer mode, 0 if disabled
+    private_sessions: u32, // Private session mode, 0 if disabled
+    separator: u32,        // Prosign id of the message separator, 0 if disabled
+}
+
+// SAFETY: DeviceConfig consists of u32 fields only, so it has no padding and all bit
+// patterns are valid.
+unsafe impl ReadableFromBytes for DeviceConfig {}
+
+// SAFETY: DeviceConfig consists of u32 fields only, so it has no padding and all bytes are
+// initialized.
+unsafe impl WritableToBytes for DeviceConfig {}
+
+/// Settings of a Device beyond DeviceConfig, whose layout is fixed by IOCTL_CREATE_DEVICE.
+/// Only configurable via configfs.
+#[derive(Clone, Copy)]
+struct DeviceOptions {
+    queue_size: usize, // Number of bytes the device is able to store
+    symbols: [u8; 2],  // Symbols of dit and dah in the morse code read from the device
+    wpm: u32,          // Words per minute readers shall key the morse code at
+}
+
+impl Default for DeviceOptions {
+    fn default() -> Self {
+        let symbols = morse::SYMBOLS.as_bytes();
+        Self {
+            queue_size: BUFFER_SIZE,
+            symbols: [symbols[0], symbols[1]],
+            wpm: DEFAULT_WPM,
+        }
+    }
+}
+
+impl DeviceOptions {
+    /// Get the byte delivered to readers for a byte of stored morse code.
+    ///
+    /// # Arguments:
+    /// * byte: The stored byte.
+    ///
+    /// # Returns:
+    /// The configured symbol for dits and dahs, any other byte as is.
+    fn output(&self, byte: u8) -> u8 {
+        match byte {
+            b'.' => self.symbols[0],
+            b'-' => self.symbols[1],
+            _ => byte,
+        }
+    }
+}
+
+// Ringbuffer containing morse code, allocated with the configured size.
+type Queue = Ring<u8, Vec<MaybeUninit<u8>>>;
+
+impl DeviceConfig {
+    /// Get the default settings given by the module parameters.
+    fn from_params() -> Self {
+        Self {
+            id: 0,
+            overwrite: *OVERWRITE.read() as u32,
+            broadcast: *BROADCAST.read() as u32,
+            multi_writer: *MULTI_WRITER.read() as u32,
+            private_sessions: *PRIVATE_SESSIONS.read() as u32,
+            separator: 0,
+        }
+    }
+}
+
+/// Mutable inner state of a Device
+struct DeviceInner {
+    readers: Vec<Reader>,                // Readers currently accessing the device
+    next_reader_id: u64,                 // Id assigned to the next reader
+    writers: usize,                      // Number of writers currently accessing the device
+    next_writer_id: u64,                 // Id assigned to the next writer
+    last_writer: Option<u64>,            // Id of the writer that stored the newest morse code
+    separator: Option<Prosign>,          // Prosign stored between messages of different writers
+    overwrite: bool,                     // Flag to indicate if old morse code is dropped if full
+    broadcast: bool,                     // Flag to indicate if multiple readers are allowed
+    multi_writer: bool,                  // Flag to indicate if multiple writers are allowed
+    private_sessions: bool,              // Flag to indicate if read/write opens get own sessions
+    policy: Policy,                      // Handling of invalid text written into the device
+    encoding: Encoding,                  // Encoding of text written into the device
+    encoding_changes: u64,               // Number of encoding changes, see DetectedOrder
+    normalization: Stages,               // Normalization of text written into the device
+    cut_numbers: CutNumbers,             // Substitutions of digits, if cut numbers are enabled
+    dictionary: Option<Arc<Dictionary>>, // Abbreviations, if modified. Otherwise the default.
+    session: bool,                       // Flag to indicate if this is a private session
+    removed: bool,                       // Flag to indicate if the device was removed
+    dropped: u64,                        // Number of bytes dropped in overwrite mode
+    stats: Statistics,                   // Statistics since creation or the last reset
+    base: u64,                           // Stream position of the oldest byte in queue
+    options: DeviceOptions,              // Queue size, output symbols and speed
+    queue: Queue,                        // Ringbuffer containing transformed morse code.
+}
+
+impl DeviceInner {
+    /// Try to create a new DeviceInner object
+    ///
+    /// # Arguments:
+    /// * config: The settings of the device. Unknown separators are ignored.
+    /// * options: The queue size, output symbols and speed of the device.
+    ///
+    /// # Returns:
+    /// On success, an Ok containing the new object,
+    /// on failure an Err containing return code ENOMEM.
+    fn try_new(config: &DeviceConfig, options: &DeviceOptions) -> Result<Self> {
+        let mut slots = Vec::try_with_capacity(options.queue_size)?;
+        // SAFETY: The capacity was reserved and MaybeUninit requires no initialization.
+        unsafe { slots.set_len(options.queue_size) };
+
+        Ok(Self {
+            readers: Vec::new(),
+            next_reader_id: 0,
+            writers: 0,
+            next_writer_id: 0,
+            last_writer: None,
+            separator: Prosign::from_id(config.separator),
+            overwrite: config.overwrite != 0,
+            broadcast: config.broadcast != 0,
+            multi_writer: config.multi_writer != 0,
+            private_sessions: config.private_sessions != 0,
+            policy: Policy::from_id(*UTF8_POLICY.read()).unwrap_or(Policy::Fail),
+            encoding: Encoding::from_id(*ENCODING.read()).unwrap_or(Encoding::Utf8),
+            encoding_changes: 0,
+            normalization: Stages::from_bits(*NORMALIZATION.read()).unwrap_or_default(),
+            cut_numbers: CutNumbers::default(),
+            dictionary: None,
+            session: false,
+            removed: false,
+            dropped: 0,
+            stats: Statistics::default(),
+            base: 0,
+            options: *options,
+            queue: Ring::with_slots(slots),
+        })
+    }
+
+    /// Try to register a new reader.
+    ///
+    /// # Returns:
+    /// On success, an Ok containing the id of the new reader,
+    /// on failure an Err containing one of the following error codes:
+    /// * ENOMEM: The reader must be allocated and this fails.
+    /// * ENODEV: The device was removed.
+    ///
+    /// # Notes:
+    /// The first reader starts at the oldest stored morse code. Additional readers
+    /// in broadcast mode receive only morse code written after they were registered.
+    fn try_add_reader(&mut self) -> Result<u64> {
+        if self.removed {
+            return Err(ENODEV);
+        }
+
+        let cursor = if self.readers.is_empty() {
+            self.base
+        } else {
+            self.end()
+        };
+
+        let id = self.next_reader_id;
+        self.readers.try_push(Reader {
+            id,
+            cursor,
+            lagged: 0,
+            rest: Ringbuffer::new(),
+        })?;
+        self.next_reader_id += 1;
+        Ok(id)
+    }
+
+    /// Unregister a reader. Morse code read by all remaining readers is removed.
+    ///
+    /// # Arguments:
+    /// * id: The id of the reader to remove.
+    fn remove_reader(&mut self, id: u64) {
+        self.readers.retain(|reader| reader.id != id);
+        self.compact();
+    }
+
+    /// Try to register a new writer.
+    ///
+    /// # Returns:
+    /// On success, an Ok containing the id of the new writer,
+    /// on failure an Err containing return code ENODEV if the device was removed.
+    fn try_add_writer(&mut self) -> Result<u64> {
+        if self.removed {
+            return Err(ENODEV);
+        }
+
+        let id = self.next_writer_id;
+        self.next_writer_id += 1;
+        self.writers += 1;
+        Ok(id)
+    }
+
+    /// Unregister a writer.
+    fn remove_writer(&mut self) {
+        self.writers -= 1;
+    }
+
+    /// Get the separator to store before a message of a writer.
+    ///
+    /// # Arguments:
+    /// * id: The id of the writer.
+    ///
+    /// # Returns:
+    /// The morse code of the configured separator, if the previous message was stored by
+    /// a different writer. Otherwise an empty slice.
+    fn separator_for(&self, id: u64) -> &'static [u8] {
+        match (self.separator, self.last_writer) {
+            (Some(separator), Some(last_writer)) if last_writer != id => {
+                separator.morse_code().as_bytes()
+            }
+            _ => &[],
+        }
+    }
+
+    /// Get a reader by its id.
+    ///
+    /// # Arguments:
+    /// * id: The id of the reader.
+    ///
+    /// # Returns:
+    /// A Some containing a reference to the reader, or None if no reader has the given id.
+    fn reader(&self, id: u64) -> Option<&Reader> {
+        self.readers.iter().find(|reader| reader.id == id)
+    }
+
+    /// Get the stream position after the newest byte in queue.
+    fn end(&self) -> u64 {
+        self.base + self.queue.len() as u64
+    }
+
+    /// Get the number of bytes a reader has not read yet.
+    ///
+    /// # Arguments:
+    /// * id: The id of the reader.
+    ///
+    /// # Returns:
+    /// The number of bytes available to the reader, 0 if no reader has the given id.
+    fn available(&self, id: u64) -> usize {
+        self.reader(id)
+            .map(|reader| reader.rest.len() + (self.end() - reader.cursor) as usize)
+            .unwrap_or(0)
+    }
+
+    /// Advance a reader, after it has read bytes from the queue.
+    ///
+    /// # Arguments:
+    /// * id: The id of the reader.
+    /// * count: The number of bytes the reader has read, starting with its kept rest.
+    fn consume(&mut self, id: u64, count: usize) {
+        if let Some(reader) = self.readers.iter_mut().find(|reader| reader.id == id) {
+            let mut kept = 0usize;
+            while kept < count && reader.rest.try_pop().is_ok() {
+                kept += 1;
+            }
+            reader.cursor += (count - kept) as u64;
+        }
+        self.compact();
+    }
+
+    /// Remove all letters from queue, that have been read by all readers.
+    ///
+    /// # Notes:
+    /// Letters are removed as a whole, so the queue always starts with a complete letter.
+    fn compact(&mut self) {
+        let min_cursor = match self.readers.iter().map(|reader| reader.cursor).min() {
+            Some(cursor) => cursor,
+            None => return,
+        };
+
+        while let Some(len) = self.first_letter_len() {
+            if self.base + len as u64 > min_cursor {
+                break;
+            }
+            self.base += self.queue.pop_until(|byte| is_letter_end(*byte)) as u64;
+        }
+    }
+
+    /// Get the length of the oldest letter in queue.
+    ///
+    /// # Returns:
+    /// A Some containing the number of bytes of the oldest letter, including its end,
+    /// or None if the queue is empty.
+    fn first_letter_len(&self) -> Option<usize> {
+        if self.queue.is_empty() {
+            return None;
+        }
+        let len = self
+            .queue
+            .iter()
+            .position(|byte| is_letter_end(*byte))
+            .map(|pos| pos + 1);
+        Some(len.unwrap_or(self.queue.len()))
+    }
+
+    /// Move all readers behind a stream position, before the morse code in front of it
+    /// is removed from the queue.
+    ///
+    /// # Arguments:
+    /// * position: The stream position to move the readers to. Must be the end of a letter.
+    ///
+    /// # Notes:
+    /// * A reader that already read the start of a letter in front of position keeps the
+    ///   unread rest of it. So readers always read letters as a whole.
+    /// * The lag of each reader is increased by the number of bytes it missed.
+    fn skip_to(&mut self, position: u64) {
+        let base = self.base;
+        for reader in self
+            .readers
+            .iter_mut()
+            .filter(|reader| reader.cursor < position)
+        {
+            let start = (reader.cursor - base) as usize;
+            let previous = start.checked_sub(1).and_then(|index| self.queue.get(index));
+            let in_letter = previous.map_or(false, |byte| !is_letter_end(*byte));
+            let mut kept = 0u64;
+            if in_letter {
+                for byte in self.queue.iter().skip(start) {
+                    if reader.rest.try_push(*byte).is_err() {
+                        break;
+                    }
+                    kept += 1;
+                    if is_letter_end(*byte) {
+                        break;
+                    }
+                }
+            }
+            reader.lagged += position - reader.cursor - kept;
+            reader.cursor = position;
+        }
+    }
+
+    /// Remove all stored morse code from the queue.
+    ///
+    /// # Returns:
+    /// The number of removed bytes.
+    ///
+    /// # Notes:
+    /// A reader that already read the start of a letter keeps the unread rest of it.
+    fn flush(&mut self) -> usize {
+        self.skip_to(self.end());
+        let flushed = self.queue.drain().count();
+        self.base += flushed as u64;
+        flushed
+    }
+
+    /// Store morse code in the queue, dropping the oldest letters if the queue is full.
+    ///
+    /// # Arguments:
+    /// * morse_code: The morse code to store.
+    ///
+    /// # Returns:
+    /// The number of bytes dropped to store the given morse code.
+    ///
+    /// # Notes:
+    /// * Letters are dropped as a whole. A reader that already read the start of the oldest
+    ///   letter keeps its unread rest, so it still reads the letter as a whole.
+    /// * Only readers that did not read the dropped morse code yet lose data. Their
+    ///   lag is increased by the number of bytes they missed.
+    fn push_overwrite(&mut self, morse_code: &[u8]) -> usize {
+        let mut dropped = 0usize;
+        for byte in morse_code {
+            if self.queue.is_full() {
+                let len = self.first_letter_len().unwrap_or(0);
+                self.skip_to(self.base + len as u64);
+                self.queue.pop_until(|byte| is_letter_end(*byte));
+                self.base += len as u64;
+                dropped += len;
+            }
+            self.queue.push_overwrite(*byte);
+        }
+        self.dropped += dropped as u64;
+        dropped
+    }
+}
+
+/// Character device implementing text to morse conversion.
+struct Device {
+    id: u16,                   // Constant Id of the device.
+    inner: Mutex<DeviceInner>, // Mutable inner device state, protected by a Mutex
+}
+
+impl Device {
+    /// Try to create a new character device.
+    ///
+    /// # Arguments:
+    /// * id: The id of the new device to create.
+    /// * config: The settings of the new device.
+    /// * options: The queue size, output symbols and speed of the new device.
+    ///
+    /// # Returns:
+    /// On success, an Arc containing a new Device,
+    /// on failure an Err containing return code ENOMEM.
+    ///
+    /// # Notes:
+    /// The device is not added to the device pool. See try_create.
+    fn try_new(id: u16, config: &DeviceConfig, options: &DeviceOptions) -> Result<Arc<Self>> {
+        let inner = Mutex::new(DeviceInner::try_new(config, options)?);
+        let device = Device { id, inner };
+        Arc::try_new(device)
+    }
+
+    /// Try to create a new private session of a device.
+    ///
+    /// # Returns:
+    /// On success, an Arc containing a new Device, not shared with any other open file.
+    /// On failure an Err containing return code ENOMEM.
+    ///
+    /// # Notes:
+    /// The session inherits the overwrite mode, the queue size, the output symbols, the
+    /// words per minute and the text handling of the device: The UTF-8 policy, the encoding,
+    /// the normalization, the cut numbers and the abbreviations.
+    fn try_new_session(&self) -> Result<Arc<Self>> {
+        let options = self.inner.lock().options;
+        let session = Device::try_new(self.id, &DeviceConfig::from_params(), &options)?;
+        let (overwrite, policy, encoding, normalization, cut_numbers, dictionary) = {
+            let inner = self.inner.lock();
+            (
+                inner.overwrite,
+                inner.policy,
+                inner.encoding,
+                inner.normalization,
+                inner.cut_numbers,
+                inner.dictionary.clone(),
+            )
+        };
+        {
+            let mut inner = session.inner.lock();
+            inner.overwrite = overwrite;
+            inner.policy = policy;
+            inner.encoding = encoding;
+            inner.normalization = normalization;
+            inner.cut_numbers = cut_numbers;
+            inner.dictionary = dictionary;
+            inner.private_sessions = false;
+            inner.session = true;
+        }
+        Ok(session)
+    }
+
+    /// Lookup a specific device.
+    ///
+    /// Arguments:
+    /// * id: The id of the device to lookup.
+    ///
+    /// Returns:
+    /// On success, an Arc to the found device,
+    /// on failure an Err containing return code ENODEV.
+    fn get(id: u16) -> Result<Arc<Device>> {
+        DEVICES_POOL
+            .lock()
+            .iter()
+            .find(|entry| entry.device.id == id)
+            .map(|entry| entry.device.clone())
+            .ok_or(ENODEV)
+    }
+
+    /// Try to create a new device and add it to the device pool.
+    ///
+    /// # Arguments:
+    /// * context: The module wide interfaces to add the interfaces of the device to.
+    /// * config: The settings of the new device. The id is ignored.
+    /// * options: The queue size, output symbols and speed of the new device.
+    /// * name: The name of the device node /dev/morse/<name>. If None, the id is used.
+    ///
+    /// # Returns:
+    /// On success, an Arc to the new device, using the lowest unused id.
+    /// On failure an Err containing one of the following error codes:
+    /// * ENOSPC: MAX_DEVICES devices exist already.
+    /// * ENOMEM: The device or its interfaces must be allocated and this fails.
+    /// * EEXIST: A device node with the same name exists already.
+    fn try_create(
+        context: &Context,
+        config: &DeviceConfig,
+        options: &DeviceOptions,
+        name: Option<&CStr>,
+    ) -> Result<Arc<Device>> {
+        let _update = POOL_UPDATE.lock();
+
+        let id = {
+            let mut pool = DEVICES_POOL.lock();
+            pool.try_reserve(1)?;
+            (0..MAX_DEVICES as u16)
+                .find(|id| pool.iter().all(|entry| entry.device.id != *id))
+                .ok_or(ENOSPC)?
+        };
+
+        Faults::of(id).reset();
+        let device = Device::try_new(id, config, options)?;
+        let name = match name {
+            Some(name) => CString::try_from_fmt(fmt!("{}", name))?,
+            None => CString::try_from_fmt(fmt!("{}", id))?,
+        };
+
+        // The class device carries the attributes of the device node, see module sysfs.
+        let sysfs = context.sysfs.try_add_device(&device, &name)?;
+        let misc = miscdev::Options::new()
+            .parent(&sysfs.device())
+            .register_new(fmt!("morse/{}", &*name), device.clone())?;
+        let entry = PoolEntry {
+            _misc: misc,
+            device: device.clone(),
+            _sysfs: sysfs,
+            #[cfg(CONFIG_DEBUG_FS)]
+            _debugfs: context.debugfs.try_add_device(id)?,
+        };
+
+        // Capacity was reserved, it should never fail.
+        DEVICES_POOL.lock().try_push(entry).unwrap();
+        Ok(device)
+    }
+
+    /// Remove a device from the device pool.
+    ///
+    /// # Arguments:
+    /// * id: The id of the device to remove.
+    /// * force: Remove the device even if it is not idle.
+    ///
+    /// # Returns:
+    /// On success, an Ok containing the unit type,
+    /// on failure an Err containing one of the following error codes:
+    /// * ENODEV: There is no device with the given id.
+    /// * EBUSY: The device is opened by at least one file and force is not set.
+    ///
+    /// # Notes:
+    /// The device is freed as soon as the last reference to it is dropped. Files opened
+    /// on a forcefully removed device keep working until they are released.
+    fn remove(id: u16, force: bool) -> Result {
+        let _update = POOL_UPDATE.lock();
+
+        let entry = {
+            let mut pool = DEVICES_POOL.lock();
+            let index = pool
+                .iter()
+                .position(|entry| entry.device.id == id)
+                .ok_or(ENODEV)?;
+            {
+                let mut inner = pool[index].device.inner.lock();
+                if !force && (!inner.readers.is_empty() || inner.writers > 0) {
+                    return Err(EBUSY);
+                }
+                inner.removed = true;
+            }
+            pool.remove(index)
+        };
+
+        // Remove interfaces of the device without holding the pool lock.
+        drop(entry);
+        Ok(())
+    }
+
+    /// Remove all devices from the device pool, regardless if they are idle.
+    fn remove_all() {
+        let _update = POOL_UPDATE.lock();
+
+        let entries = core::mem::take(&mut *DEVICES_POOL.lock());
+        for entry in entries.iter() {
+            entry.device.inner.lock().removed = true;
+        }
+
+        // Remove interfaces of the devices without holding the pool lock.
+        drop(entries);
+    }
+}
+
+/// Entry of the device pool, owning a device and its interfaces.
+struct PoolEntry {
+    _misc: Pin<Box<miscdev::Registration<Device>>>, // Device node, removed first
+    device: Arc<Device>,                            // The pooled device
+    _sysfs: SysfsDir,                               // Class device, parent of the node
+    #[cfg(CONFIG_DEBUG_FS)]
+    _debugfs: DebugfsDir,                           // debugfs directory of the device
+}
+
+/// Access of a Device via an open file.
+struct Handle {
+    device: Arc<Device>,                    // Device accessed by the file.
+    reader: Option<u64>,                    // Id of the reader, if the file was opened for reading.
+    writer: Option<u64>,                    // Id of the writer, if the file was opened for writing.
+    byte_order: Mutex<DetectedOrder>,       // Byte order of UTF-16 text, detected on first write.
+    normalization: Mutex<normalize::State>, // Normalization state after the written text.
+    word: Mutex<bool>,                      // Flag if the written text ends within a word.
+}
+
+/// Byte order detected by a file. Changing the encoding of the device restarts the detection
+/// of all its files, so the byte order is only valid for the encoding it was detected in.
+#[derive(Clone, Copy, Default)]
+struct DetectedOrder {
+    encoding_changes: u64,         // Number of encoding changes of the device on detection
+    byte_order: Option<ByteOrder>, // The detected byte order, None if not detected yet
+}
+
+impl DetectedOrder {
+    /// Get the byte order to continue with.
+    ///
+    /// # Arguments:
+    /// * encoding_changes: The current number of encoding changes of the device.
+    ///
+    /// # Returns:
+    /// The detected byte order, None if it was not detected yet or the encoding changed since.
+    fn current(&self, encoding_changes: u64) -> Option<ByteOrder> {
+        match self.encoding_changes == encoding_changes {
+            true => self.byte_order,
+            false => None,
+        }
+    }
+}
+
+impl Handle {
+    /// Get the access mode of the file.
+    ///
+    /// # Returns:
+    /// O_RDWR, O_RDONLY or O_WRONLY depending on the registered reader and writer.
+    fn access_mode(&self) -> u32 {
+        match (self.reader, self.writer) {
+            (Some(_), Some(_)) => O_RDWR,
+            (Some(_), None) => O_RDONLY,
+            _ => O_WRONLY,
+        }
+    }
+}
+
+#[vtable]
+impl file::Operations for Device {
+    type OpenData = Arc<Device>;
+    type Data = Box<Handle>;
+
+    /// Syscall open implementation
+    ///
+    /// # Arguments:
+    /// * device: The device the opened node was registered for.
+    /// * file: Reference kernel file data structure.
+    ///
+    /// # Returns:
+    /// On success: An Ok containing a Handle to the Device, on failure
+    /// an Err containing one of the following error codes:
+    /// * ENODEV: The device was removed.
+    /// * ENOMEM: A reader or private session must be allocated and this fails.
+    /// * EACCESS: Opening the device violates exclusive access rules.
+    ///
+    /// # Notes:
+    /// To function properly, this device relies on exclusive access for reading and/or writing.
+    /// In broadcast mode, multiple readers are allowed. Each reader receives all morse code.
+    /// In multi writer mode, multiple writers are allowed. Each write is stored atomically.
+    /// In private session mode, each read/write access gets its own session. Morse code
+    /// written into a session can only be read back via the same file.
+    fn open(device: &Self::OpenData, file: &file::File) -> Result<Self::Data> {
+        let device = device.clone();
+        log_info!("Open device {}\n", device.id);
+        let mut handle = Box::try_new(Handle {
+            device: device.clone(),
+            reader: None,
+            writer: None,
+            byte_order: Mutex::new(DetectedOrder::default()),
+            normalization: Mutex::new(normalize::State::default()),
+            word: Mutex::new(false),
+        })?;
+
+        // Handle requested access mode
+        let private_sessions = device.inner.lock().private_sessions;
+        match file.flags() & O_ACCMODE {
+            // Read only access attempt
+            O_RDONLY => {
+                let mut inner = device.inner.lock();
+                if !inner.broadcast && !inner.readers.is_empty() {
+                    log_err!(
+                        "Failed to get read access for Device {}. Already in use.\n",
+                        device.id
+                    );
+                    return Err(EACCES);
+                } else {
+                    log_info!("Mark Device {} as read accessed.\n", device.id);
+                    handle.reader = Some(inner.try_add_reader()?);
+                }
+            }
+            // Write only access attempt
+            O_WRONLY => {
+                let mut inner = device.inner.lock();
+                if !inner.multi_writer && inner.writers > 0 {
+                    log_err!(
+                        "Failed to get write access for Device {}. Already in use.\n",
+                        device.id
+                    );
+                    return Err(EACCES);
+                } else {
+                    log_info!("Mark Device {} as write accessed.\n", device.id);
+                    handle.writer = Some(inner.try_add_writer()?);
+                }
+            }
+            // Read/write access attempt with private session
+            O_RDWR if private_sessions => {
+                log_info!("Open private session on Device {}.\n", device.id);
+                if device.inner.lock().removed {
+                    return Err(ENODEV);
+                }
+                let session = device.try_new_session()?;
+                {
+                    let mut inner = session.inner.lock();
+                    handle.reader = Some(inner.try_add_reader()?);
+                    handle.writer = Some(inner.try_add_writer()?);
+                }
+                handle.device = session;
+            }
+            // Read/write access attempt
+            O_RDWR => {
+                let mut inner = device.inner.lock();
+                if (!inner.broadcast && !inner.readers.is_empty())
+                    || (!inner.multi_writer && inner.writers > 0)
+                {
+                    log_err!(
+                        "Failed to get read/write access for Device {}. Already in use.\n",
+                        device.id
+                    );
+                    return Err(EACCES);
+                } else {
+                    log_info!("Mark Device {} as read/write accessed.\n", device.id);
+                    handle.reader = Some(inner.try_add_reader()?);
+                    handle.writer = Some(inner.try_add_writer()?);
+                }
+            }
+            _ => {
+                log_err!("Unexpected access flags. Return Error.\n");
+                return Err(EACCES);
+            }
+        };
+
+        trace::open(device.id, handle.access_mode());
+        log_info!("Opened device {} successfully\n", device.id);
+        Ok(handle)
+    }
+
+    /// Syscall release implementation
+    ///
+    /// # Arguments:
+    /// * handle: Handle to Device to release
+    /// * file: Reference kernel file data structure.
+    ///
+    /// # Notes:
+    /// This function unregisters the reader and writer registered in open.
+    fn release(handle: Self::Data, _file: &file::File) {
+        let device = &handle.device;
+        log_info!("Release device {}\n", device.id);
+
+        let mut inner = device.inner.lock();
+        let was_empty = inner.queue.is_empty();
+        if let Some(id) = handle.reader {
+            log_info!("Unmark Device {} as read accessed.\n", device.id);
+            inner.remove_reader(id);
+        }
+        if !was_empty && inner.queue.is_empty() {
+            trace::queue_empty(device.id);
+        }
+        if handle.writer.is_some() {
+            log_info!("Unmark Device {} as write accessed.\n", device.id);
+            inner.remove_writer();
+        }
+        drop(inner);
+
+        // Morse code might have been held back for the released reader.
+        WRITE_CONDITION.notify_all();
+        trace::release(device.id, handle.access_mode());
+        log_info!("Released device {} successfully\n", device.id);
+    }
+
+    /// Syscall write implementation
+    ///
+    /// # Arguments:
+    /// * handle: Reference to Handle of the Device to write data into.
+    /// * _file: Reference kernel file data structure.
+    /// * buffer: Reference to buffered reader containing the data to write.
+    /// * offset: Buffer offset parameter.
+    ///
+    /// # Returns:
+    /// On success: An Ok containing the number of successfully written bytes, on failure
+    /// an Err containing one of the following error codes:
+    /// * EINVAL: Given buffer starts with an invalid sequence and the policy is Fail.
+    /// * EINVAL: Given buffer not enough bytes to contain a codepoint.
+    /// * EBADF: The file was not opened for writing.
+    /// * ENOMEM: Temporary data structures ran out of memory.
+    /// * EAGAIN: The device is a private session without space for the next letter.
+    ///
+    /// The number of written bytes is exact: It covers all bytes whose morse code was stored,
+    /// including skipped or replaced invalid sequences, and nothing else. Bytes read from the
+    /// buffer beyond that are not stored. A retry starting at the returned offset neither loses
+    /// nor duplicates bytes. Errors are only returned if no byte was written.
+    ///
+    /// # Notes:
+    /// * In overwrite mode, write never blocks. If the device is full, the oldest letters
+    ///   are dropped to make space for the new morse code.
+    /// * In multi writer mode, each write is stored as an atomic message. The message size is
+    ///   limited by the device buffer, so larger writes are stored partially. If a separator is
+    ///   configured, it is stored between messages of different writers.
+    /// * In a private session, each write stores a single chunk as well, limited to the free
+    ///   space. A session never blocks, since only its own file drains it. The morse code must
+    ///   be read back before the session is able to store more than the buffer size.
+    /// * Text is decoded according to the encoding of the device. The byte order of UTF-16 is
+    ///   detected from a BOM on the first write of the file, little endian is assumed otherwise.
+    ///   BOMs are not converted into morse code.
+    /// * Decoded text is normalized according to the normalization stages of the device.
+    ///   Runs of whitespace and fields of cut numbers continue across writes of the same file.
+    /// * If compression is enabled, phrases are replaced by their abbreviations at word
+    ///   boundaries. A phrase split across writes or chunks is not replaced.
+    /// * Invalid sequences are handled according to the UTF-8 policy of the device, in any
+    ///   encoding. Bytes above 0x7f are invalid in ASCII. Invalid sequences either stop the write,
+    ///   are replaced with U+FFFD or are skipped. A truncated sequence at the end of the buffer
+    ///   always stops the write, regardless of the policy.
+    /// * write is meant from a user space perspective. If a process from user space wants to write
+    ///   into a file, the file must read from content from user space.
+    /// * From a user space side, buffered data may be passed chunk wise to the read function.
+    ///   If UTF-8 verification fails this does not mean that entire byte sequence is garbage,
+    ///   if might be a case of a miss-aligned buffer and the next attempt contains all expected
+    ///   bytes -> If any errors occur and there have been successfully written bytes, return the
+    ///   number of written bytes instead of an error.
+    /// * See ABI/dev-morse for the contract towards user space.
+    fn write(
+        handle: <Self::Data as ForeignOwnable>::Borrowed<'_>,
+        _file: &file::File,
+        buffer: &mut impl IoBufferReader,
+        offset: u64,
+    ) -> Result<usize> {
+        let device = &handle.device;
+        let faults = Faults::of(device.id);
+        let writer = handle.writer.ok_or(EBADF)?;
+        log_info!("Try to write {} into device {}\n", buffer.len(), device.id);
+        log_info!("Write: Offset is {}\n", offset);
+
+        // Parse buffer chunk wise. Each chunk is converted before it is stored as a whole,
+        // so morse code of concurrent writers is never interleaved. In multi writer mode,
+        // a single chunk is stored to keep each write an atomic message.
+        let mut total_bytes_read = 0usize;
+        let mut total_bytes_dropped = 0usize;
+        let mut morse_code = Vec::try_with_capacity(CHUNK_SIZE)?;
+        let (policy, encoding, encoding_changes, mut normalizer, compress, dictionary) = {
+            let inner = device.inner.lock();
+            let normalizer = Normalizer::new(
+                inner.normalization,
+                inner.cut_numbers,
+                *handle.normalization.lock(),
+            );
+            let compress = inner.normalization.contains(Stages::COMPRESS);
+            (
+                inner.policy,
+                inner.encoding,
+                inner.encoding_changes,
+                normalizer,
+                compress,
+                inner.dictionary.clone(),
+            )
+        };
+        let dictionary = dictionary.as_deref().unwrap_or(&DEFAULT_DICTIONARY);
+        let mut compressor = Compressor::new(compress.then_some(dictionary), *handle.word.lock());
+        let byte_order = handle.byte_order.lock().current(encoding_changes);
+        let mut decoder = Decoder::new(encoding, byte_order);
+        let detected = |decoder: &Decoder| DetectedOrder {
+            encoding_changes,
+            byte_order: decoder.byte_order(),
+        };
+        let mut drained = false;
+        while !drained {
+            // Parse buffer char by char. Since a char might have a variable length encoding,
+            // try to extract a char from buffer, verify its encoding and convert
+            // it afterwards to the associated morse code representation until one
+            // of the following events happen:
+            // - The given buffer is drained
+            // - The chunk is full.
+            // - Or something else has gone wrong.
+            // Invalid text is handled according to the policy of the device.
+            // A private session is drained by its own file only, so it never waits for space.
+            // Its chunk is limited to the free space instead.
+            let space = {
+                let inner = device.inner.lock();
+                match inner.session && !inner.overwrite {
+                    true => inner.queue.free().min(CHUNK_SIZE),
+                    false => CHUNK_SIZE,
+                }
+            };
+            morse_code.clear();
+            let read_char = || {
+                if faults.fail_read_char() {
+                    log_info!("Inject fault: Fail to read char.\n");
+                    return buffer.read::<u8>().ok().map(|_| Sequence {
+                        char: Err(DecodeError::Invalid),
+                        len: 1,
+                    });
+                }
+                try_read_char(&mut decoder, buffer)
+            };
+            let store = |char, code: &str| {
+                pr_debug!("Try to store given char '{}' as '{}'\n", char, code);
+                trace::char_converted(device.id, char, code);
+                morse_code.try_extend_from_slice(code.as_bytes())
+            };
+            let conversion = match convert(
+                space,
+                policy,
+                &mut normalizer,
+                &mut compressor,
+                read_char,
+                store,
+            ) {
+                Ok(conversion) => conversion,
+                // Morse code of previous chunks was stored already. Report it instead.
+                Err(errno) if total_bytes_read == 0 => return Err(errno),
+                Err(_) => break,
+            };
+            let bytes_read = conversion.bytes_read;
+
+            // A truncated character is not counted as invalid, the next write might complete it.
+            match conversion.stop {
+                Stop::Invalid(DecodeError::Invalid) => {
+                    if encoding == Encoding::Ascii {
+                        log_err!("Byte above 0x7f given. It is no valid ASCII character.\n");
+                    }
+                    device.inner.lock().stats.invalid_utf8 += 1;
+                }
+                Stop::Invalid(DecodeError::Truncated) => {
+                    log_info!(
+                        "Buffer ends within a {} character. Stop before it.\n",
+                        encoding.name()
+                    );
+                }
+                _ => (),
+            }
+            if conversion.stop != Stop::Full {
+                if total_bytes_read + bytes_read == 0 {
+                    log_err!(
+                        "Failed to read bytes. No valid {} character given.\n",
+                        encoding.name()
+                    );
+                    return Err(EINVAL);
+                }
+                drained = true;
+            }
+            if morse_code.is_empty() && conversion.stop == Stop::Full {
+                log_info!(
+                    "Session of device {} is full. Read morse code first.\n",
+                    device.id
+                );
+                return Err(EAGAIN);
+            }
+            if morse_code.is_empty() {
+                // Only skipped invalid sequences, BOMs and dropped characters were read.
+                device.inner.lock().stats.invalid_utf8 += conversion.invalid_utf8;
+                total_bytes_read += bytes_read;
+                *handle.byte_order.lock() = detected(&decoder);
+                *handle.normalization.lock() = normalizer.state();
+                *handle.word.lock() = compressor.word();
+                break;
+            }
+
+            // Wait until there is space to store the chunk and a separator if needed.
+            // In overwrite mode, old morse code is dropped instead.
+            let mut inner = device.inner.lock();
+            let mut separator = inner.separator_for(writer);
+            let mut blocked = false;
+            while !inner.overwrite && inner.queue.free() < separator.len() + morse_code.len() {
+                if inner.session {
+                    // Another write on the same file took the space. Nothing else drains it.
+                    log_info!("Session of device {} is full. Return.\n", device.id);
+                    return Err(EAGAIN);
+                }
+                log_info!(
+                    "Device {} buffer is full. Wait until space is available.\n",
+                    device.id
+                );
+
+                if !blocked {
+                    inner.stats.writer_blocks += 1;
+                    blocked = true;
+                }
+
+                trace::writer_wait(device.id);
+                let signaled = WRITE_CONDITION.wait(&mut inner);
+                trace::writer_wakeup(device.id);
+                if signaled {
+                    log_info!("Signal received, chunk was not written. Return.\n");
+                    inner.stats.interrupts += 1;
+                    return Ok(total_bytes_read);
+                }
+                separator = inner.separator_for(writer);
+            }
+
+            // Store separator and morse code.
+            if faults.fail_push() {
+                log_info!("Inject fault: Fail to store morse code.\n");
+                if total_bytes_read == 0 {
+                    return Err(ENOMEM);
+                }
+                break;
+            }
+
+            let was_full = inner.queue.is_full();
+            if inner.overwrite {
+                total_bytes_dropped += inner.push_overwrite(separator);
+                total_bytes_dropped += inner.push_overwrite(&morse_code);
+            } else {
+                separator
+                    .iter()
+                    .chain(morse_code.iter())
+                    .try_for_each(|byte| inner.queue.try_push(*byte))
+                    .unwrap(); // Due to the previous check, it should never fail.
+            }
+            if !was_full && inner.queue.is_full() {
+                trace::queue_full(device.id);
+            }
+            inner.last_writer = Some(writer);
+            inner.stats.bytes_in += bytes_read as u64;
+            inner.stats.chars_converted += conversion.chars_converted;
+            inner.stats.unknown_chars += conversion.unknown_chars;
+            inner.stats.invalid_utf8 += conversion.invalid_utf8;
+            total_bytes_read += bytes_read;
+            *handle.byte_order.lock() = detected(&decoder);
+            *handle.normalization.lock() = normalizer.state();
+            *handle.word.lock() = compressor.word();
+
+            let single_chunk = inner.multi_writer || inner.session;
+            drop(inner);
+
+            faults.delay_wakeup();
+            READ_CONDITION.notify_all();
+            if single_chunk {
+                break;
+            }
+        }
+
+        if total_bytes_dropped > 0 {
+            log_info!(
+                "Device {} was full. Dropped {} bytes of morse code.\n",
+                device.id,
+                total_bytes_dropped
+            );
+        }
+
+        log_info!(
+            "Written {} bytes into device {}.\n",
+            total_bytes_read,
+            device.id
+        );
+
+        Ok(total_bytes_read)
+    }
+
+    /// Syscall read implementation
+    ///
+    /// # Arguments:
+    /// * handle: Reference to Handle of the Device to read data from.
+    /// * _file: Reference kernel file data structure.
+    /// * buffer: Reference to buffered write containing read data after the call.
+    /// * offset: Buffer offset parameter.
+    ///
+    /// # Returns:
+    /// On success: An Ok containing the number of successfully read bytes, on failure
+    /// an Err containing one of the following error codes:
+    /// * EBADF: The file was not opened for reading.
+    ///
+    /// # Notes:
+    /// * read is meant from a user space perspective. If a process from user space wants to read
+    ///   from a file, the file must write its contents to user space.
+    /// * Each reader reads from its own position in the morse code stream. Morse code is
+    ///   removed from the device after it was read by all readers.
+    /// * Letters are never cut. A reader that read the start of a letter dropped in overwrite
+    ///   mode or flushed, still reads its rest.
+    fn read(
+        handle: <Self::Data as ForeignOwnable>::Borrowed<'_>,
+        _file: &file::File,
+        buffer: &mut impl IoBufferWriter,
+        offset: u64,
+    ) -> Result<usize> {
+        let device = &handle.device;
+        let reader = handle.reader.ok_or(EBADF)?;
+        log_info!("Try to read {} from device {}\n", buffer.len(), device.id);
+        log_info!("Read: Offset is {}\n", offset);
+
+        // Wait sleep until read condition is fulfilled. Or a signal was received.
+        let mut inner = device.inner.lock();
+        let mut blocked = false;
+
+        while inner.available(reader) == 0 {
+            log_info!(
+                "Device {} is empty. Wait sleep until data is available.\n",
+                device.id
+            );
+
+            if !blocked {
+                inner.stats.reader_blocks += 1;
+                blocked = true;
+            }
+
+            trace::reader_wait(device.id);
+            let signaled = READ_CONDITION.wait(&mut inner);
+            trace::reader_wakeup(device.id);
+            if signaled {
+                log_info!("Signal received, nothing to read. Return\n");
+                inner.stats.interrupts += 1;
+                return Ok(0);
+            }
+        }
+        log_info!("Device {} has data. Read as much as possible.\n", device.id);
+
+        // Transfer bytes from queue to buffer, starting at the kept rest of a dropped letter and
+        // the readers position, until either the buffer is full or all available bytes are
+        // transferred.
+        let mut total_bytes_written = 0usize;
+        if let Some(state) = inner.reader(reader) {
+            let start = (state.cursor - inner.base) as usize;
+            for byte in state.rest.iter().chain(inner.queue.iter().skip(start)) {
+                if buffer.is_empty() || buffer.write(&inner.options.output(*byte)).is_err() {
+                    break;
+                }
+                total_bytes_written += 1;
+            }
+        }
+        inner.consume(reader, total_bytes_written);
+        inner.stats.bytes_out += total_bytes_written as u64;
+        if inner.queue.is_empty() {
+            trace::queue_empty(device.id);
+        }
+        drop(inner);
+
+        log_info!(
+            "Read {} from device {}. Notify writers.\n",
+            total_bytes_written,
+            device.id
+        );
+        Faults::of(device.id).delay_wakeup();
+        WRITE_CONDITION.notify_all();
+        Ok(total_bytes_written)
+    }
+
+    /// Syscall ioctl implementation
+    ///
+    /// # Arguments:
+    /// * handle: Reference to Handle of the Device the command is issued on.
+    /// * file: Reference kernel file data structure.
+    /// * cmd: The ioctl command to handle. See module ioctl for all supported commands.
+    ///
+    /// # Returns:
+    /// On success: An Ok containing 0, on failure an Err containing one of
+    /// the following error codes:
+    /// * ENOTTY: The given command is not supported.
+    /// * EFAULT: Data could not be transferred from/to user space.
+    fn ioctl(
+        handle: <Self::Data as ForeignOwnable>::Borrowed<'_>,
+        file: &file::File,
+        cmd: &mut IoctlCommand,
+    ) -> Result<i32> {
+        cmd.dispatch::<Handle>(handle, file)
+    }
+}
+
+/// Try to read a char from given buffer
+///
+/// # Arguments:
+/// * decoder: The decoder of the buffer's encoding, keeping bytes read ahead between calls.
+/// * buffer: The buffer to read from.
+///
+/// # Returns:
+/// None if the given buffer is drained. Otherwise a Some containing the sequence read
+/// from the buffer and its length. Its char is either an Ok with the char read from the buffer,
+/// or an Err containing one of the following errors:
+/// * DecodeError::Invalid: The buffer contains an invalid sequence. It ends before the
+///   first byte that does not belong to it, so a valid char following it is never lost.
+/// * DecodeError::Truncated: The buffer is to short to contain the sequence.
+fn try_read_char(decoder: &mut Decoder, buffer: &mut impl IoBufferReader) -> Option<Sequence> {
+    decoder.decode(|| buffer.read::<u8>().ok())
+}
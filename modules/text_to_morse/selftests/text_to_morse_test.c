@@ -0,0 +1,673 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+/*
+ * Userspace selftests of the module.
+ *
+ * Each test creates its own device via the control device /dev/text_to_morse_ctl, so the
+ * tests do not depend on the module parameters and do not interfere with each other.
+ * The results are reported in TAP format. Use `make selftest_module` to run them in the
+ * development environment.
+ */
+
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#include "kselftest.h"
+
+#define CONTROL_DEVICE "/dev/text_to_morse_ctl"
+#define DEVICE_FORMAT "/dev/morse/%u"
+#define TTY_DEVICE "/dev/ttyMORSE0"
+#define PTY_MASTER "/dev/ptmx"
+
+/* Number of line discipline N_MORSE, see module parameter LDISC in main.rs */
+#define N_MORSE 29
+
+/* Size of the device buffer, see BUFFER_SIZE in main.rs */
+#define BUFFER_SIZE 256
+
+/* Size of the text buffer of N_MORSE, see RECEIVE_BUFFER_SIZE in ldisc.rs */
+#define RECEIVE_BUFFER_SIZE 1024
+
+/* Seconds until the whole test run is aborted, in case a test blocks forever */
+#define TIMEOUT 60
+
+/* Settings of a device, see DeviceConfig in main.rs */
+struct device_config {
+	uint32_t id;
+	uint32_t overwrite;
+	uint32_t broadcast;
+	uint32_t multi_writer;
+	uint32_t private_sessions;
+	uint32_t separator;
+};
+
+/* Statistics of a device, see Statistics in main.rs */
+struct device_stats {
+	uint64_t bytes_in;
+	uint64_t chars_converted;
+	uint64_t bytes_out;
+	uint64_t unknown_chars;
+	uint64_t invalid_utf8;
+	uint64_t writer_blocks;
+	uint64_t reader_blocks;
+	uint64_t interrupts;
+};
+
+/* ioctl commands, see ioctl.rs and control.rs */
+#define IOCTL_TYPE 'M'
+#define IOCTL_GET_STATS _IOR(IOCTL_TYPE, 0x0F, struct device_stats)
+#define IOCTL_SET_ENCODING _IOW(IOCTL_TYPE, 0x13, uint32_t)
+#define IOCTL_SET_NORMALIZATION _IOW(IOCTL_TYPE, 0x15, uint32_t)
+#define IOCTL_SET_CUT_NUMBERS _IOW(IOCTL_TYPE, 0x17, uint8_t[10])
+#define IOCTL_ADD_ABBREVIATION _IOW(IOCTL_TYPE, 0x18, struct abbreviation)
+#define IOCTL_CLEAR_ABBREVIATIONS _IO(IOCTL_TYPE, 0x19)
+#define IOCTL_SET_EXPAND_CUT_NUMBERS _IOW(IOCTL_TYPE, 0x1B, uint32_t)
+#define IOCTL_CREATE_DEVICE _IOWR(IOCTL_TYPE, 0x20, struct device_config)
+#define IOCTL_REMOVE_DEVICE _IOW(IOCTL_TYPE, 0x21, uint32_t)
+
+/* Encoding ids, see encoding.rs */
+#define ENCODING_LATIN1 1
+#define ENCODING_UTF16 2
+#define ENCODING_ASCII 3
+
+/* Normalization stages, see normalize.rs */
+#define NORMALIZATION_CUT_FIELDS 0x10
+#define NORMALIZATION_COMPRESS 0x20
+#define NORMALIZATION_ALL 0x3f
+
+/* Argument of IOCTL_ADD_ABBREVIATION, see ioctl.rs */
+struct abbreviation {
+	char phrase[24];
+	char abbreviation[8];
+};
+
+/* Text and the morse code it is converted to */
+struct golden {
+	const char *text;
+	const char *morse_code;
+};
+
+static const struct golden golden_corpus[] = {
+	{ "SOS", "... --- ... " },
+	{ "sos", "... --- ... " },
+	{ "12:30", ".---- ..--- ---... ...-- ----- " },
+	{ "Grüße", "--. .-. ..-- ...--.. . " },
+	{ "a#€\U0001F600", ".- ........ ........ ........ " },
+	{ "Hello, World!\n",
+	  ".... . .-.. .-.. --- --..--    .-- --- .-. .-.. -.. -.-.-- \n" },
+};
+
+static int control = -1;
+
+static void on_signal(int signal)
+{
+	(void)signal;
+}
+
+/* Create a device with default settings. Returns its id, or -1 on failure. */
+static int create_device(void)
+{
+	struct device_config config = { 0 };
+
+	if (ioctl(control, IOCTL_CREATE_DEVICE, &config) < 0) {
+		ksft_print_msg("Failed to create device: %s\n", strerror(errno));
+		return -1;
+	}
+	return config.id;
+}
+
+/* Remove a device. Returns 0 on success, or the errno on failure. */
+static int remove_device(uint32_t id)
+{
+	return ioctl(control, IOCTL_REMOVE_DEVICE, &id) < 0 ? errno : 0;
+}
+
+/* Open a device. Returns the file descriptor, or -1 with errno set on failure. */
+static int open_device(int id, int flags)
+{
+	char path[32];
+
+	snprintf(path, sizeof(path), DEVICE_FORMAT, id);
+	return open(path, flags);
+}
+
+/* Read exactly len bytes from a device. Returns 0 if they match the expected bytes. */
+static int read_expected(int fd, const char *expected, size_t len)
+{
+	char buffer[BUFFER_SIZE];
+	size_t total = 0;
+
+	while (total < len) {
+		size_t count = len - total < sizeof(buffer) ? len - total : sizeof(buffer);
+		ssize_t bytes = read(fd, buffer, count);
+
+		if (bytes <= 0 || memcmp(buffer, expected + total, bytes))
+			return -1;
+		total += bytes;
+	}
+	return 0;
+}
+
+/* Wait until a reader or writer blocked on a device. Returns 0 on success. */
+static int wait_for_block(int fd, int writer)
+{
+	struct timespec delay = { .tv_nsec = 10 * 1000 * 1000 };
+	struct device_stats stats;
+	int tries;
+
+	for (tries = 0; tries < 500; tries++) {
+		if (ioctl(fd, IOCTL_GET_STATS, &stats) < 0)
+			return -1;
+		if ((writer ? stats.writer_blocks : stats.reader_blocks) > 0)
+			return 0;
+		nanosleep(&delay, NULL);
+	}
+	return -1;
+}
+
+/* Wait for a child process. Returns its exit status, or -1 if it did not exit normally. */
+static int wait_for_child(pid_t child)
+{
+	int status;
+
+	if (waitpid(child, &status, 0) != child || !WIFEXITED(status))
+		return -1;
+	return WEXITSTATUS(status);
+}
+
+/* Check if a child process is still running, e.g. blocked in a syscall. */
+static int child_running(pid_t child)
+{
+	int status;
+
+	return waitpid(child, &status, WNOHANG) == 0;
+}
+
+static void test_golden_corpus(void)
+{
+	int id = create_device();
+	int fd = open_device(id, O_RDWR);
+	size_t i;
+	int pass = fd >= 0;
+
+	for (i = 0; pass && i < ARRAY_SIZE(golden_corpus); i++) {
+		const struct golden *golden = &golden_corpus[i];
+		ssize_t len = strlen(golden->text);
+
+		pass = write(fd, golden->text, len) == len &&
+		       !read_expected(fd, golden->morse_code, strlen(golden->morse_code));
+		if (!pass)
+			ksft_print_msg("Conversion of \"%s\" failed\n", golden->text);
+	}
+
+	/* Text without a single valid UTF-8 character is rejected */
+	pass = pass && write(fd, "\xc0\xaf", 2) < 0 && errno == EINVAL;
+
+	close(fd);
+	pass = pass && !remove_device(id);
+	ksft_test_result(pass, "golden_corpus\n");
+}
+
+static void test_partial_write(void)
+{
+	int id = create_device();
+	int fd = open_device(id, O_RDWR);
+	int pass = fd >= 0;
+
+	/* A write ends before an invalid sequence, a retry starting at it fails */
+	pass = pass && write(fd, "ab\xff" "cd", 5) == 2 && !read_expected(fd, ".- -... ", 8);
+	pass = pass && write(fd, "\xff" "cd", 3) < 0 && errno == EINVAL;
+
+	/* A write ends before a truncated sequence, a retry completing it succeeds */
+	pass = pass && write(fd, "a\xc3", 2) == 1 && !read_expected(fd, ".- ", 3);
+	pass = pass && write(fd, "\xc3\xa4", 2) == 2 && !read_expected(fd, ".-.- ", 5);
+
+	close(fd);
+	pass = pass && !remove_device(id);
+	ksft_test_result(pass, "partial_write\n");
+}
+
+static int set_encoding(int fd, uint32_t encoding)
+{
+	return ioctl(fd, IOCTL_SET_ENCODING, &encoding) < 0 ? errno : 0;
+}
+
+static void test_encodings(void)
+{
+	int id = create_device();
+	int fd = open_device(id, O_RDWR);
+	int pass = fd >= 0;
+
+	/* Each Latin-1 byte is a character */
+	pass = pass && !set_encoding(fd, ENCODING_LATIN1);
+	pass = pass && write(fd, "\xe4", 1) == 1 && !read_expected(fd, ".-.- ", 5);
+
+	/* ASCII rejects bytes above 0x7f */
+	pass = pass && !set_encoding(fd, ENCODING_ASCII);
+	pass = pass && write(fd, "a\xe4", 2) == 1 && !read_expected(fd, ".- ", 3);
+	pass = pass && write(fd, "\xe4", 1) < 0 && errno == EINVAL;
+
+	/* The byte order of UTF-16 is detected once, the BOM is not converted */
+	pass = pass && !set_encoding(fd, ENCODING_UTF16);
+	pass = pass && write(fd, "\xfe\xff\0S\0O", 6) == 6 && !read_expected(fd, "... --- ", 8);
+	pass = pass && write(fd, "\0S", 2) == 2 && !read_expected(fd, "... ", 4);
+
+	close(fd);
+	pass = pass && !remove_device(id);
+	ksft_test_result(pass, "encodings\n");
+}
+
+static void test_normalization(void)
+{
+	const char *text = "\u201c599\u201d\t\u00a0tnx";
+	const char *morse_code = ".-..-. . -. -. .-..-.    - -. -..- ";
+	uint32_t stages = NORMALIZATION_ALL;
+	int id = create_device();
+	int fd = open_device(id, O_RDWR);
+	int pass = fd >= 0 && !ioctl(fd, IOCTL_SET_NORMALIZATION, &stages);
+
+	/* Quotes and spaces are mapped, whitespace collapsed and digits cut */
+	pass = pass && write(fd, text, strlen(text)) == (ssize_t)strlen(text);
+	pass = pass && !read_expected(fd, morse_code, strlen(morse_code));
+
+	/* Whitespace is collapsed across writes */
+	pass = pass && write(fd, " ", 1) == 1 && write(fd, "\t", 1) == 1 && write(fd, "e", 1) == 1;
+	pass = pass && !read_expected(fd, "   . ", 5);
+
+	close(fd);
+	pass = pass && !remove_device(id);
+	ksft_test_result(pass, "normalization\n");
+}
+
+static void test_cut_numbers(void)
+{
+	uint32_t stages = NORMALIZATION_CUT_FIELDS;
+	uint8_t letters[10] = { 'O', 'A', 'U', '3', '4', '5', '6', 'B', 'D', 'N' };
+	uint8_t ambiguous[10] = { 'T', 'A', 'U', 'V', '4', 'E', '6', 'B', 'D', 'T' };
+	int id = create_device();
+	int fd = open_device(id, O_RDWR);
+	int pass = fd >= 0 && !ioctl(fd, IOCTL_SET_NORMALIZATION, &stages);
+
+	/* Digits are cut only within fields, the default cuts 9 to N */
+	pass = pass && write(fd, "9 [9]", 5) == 5;
+	pass = pass && !read_expected(fd, "----.    -. ", 12);
+
+	/* Each letter may substitute a single digit */
+	pass = pass && ioctl(fd, IOCTL_SET_CUT_NUMBERS, ambiguous) < 0 && errno == EINVAL;
+	pass = pass && !ioctl(fd, IOCTL_SET_CUT_NUMBERS, letters);
+	pass = pass && write(fd, "[05]", 4) == 4 && !read_expected(fd, "--- ..... ", 10);
+
+	close(fd);
+	pass = pass && !remove_device(id);
+	ksft_test_result(pass, "cut_numbers\n");
+}
+
+static void test_compression(void)
+{
+	uint32_t stages = NORMALIZATION_COMPRESS;
+	struct abbreviation qsl = { "qsl card", "QSL" };
+	struct abbreviation invalid = { "qsl!", "QSL" };
+	int id = create_device();
+	int fd = open_device(id, O_RDWR);
+	int pass = fd >= 0 && !ioctl(fd, IOCTL_SET_NORMALIZATION, &stages);
+
+	/* Phrases are replaced by default abbreviations, THANKS becomes TNX */
+	pass = pass && write(fd, "THANKS", 6) == 6 && !read_expected(fd, "- -. -..- ", 10);
+
+	/* Added abbreviations are replaced as well, malformed ones are rejected */
+	pass = pass && ioctl(fd, IOCTL_ADD_ABBREVIATION, &invalid) < 0 && errno == EINVAL;
+	pass = pass && !ioctl(fd, IOCTL_ADD_ABBREVIATION, &qsl);
+	pass = pass && write(fd, " qsl card", 9) == 9;
+	pass = pass && !read_expected(fd, "   --.- ... .-.. ", 17);
+
+	/* Without abbreviations, text is converted as is */
+	pass = pass && !ioctl(fd, IOCTL_CLEAR_ABBREVIATIONS, 0);
+	pass = pass && write(fd, " thanks", 7) == 7;
+	pass = pass && !read_expected(fd, "   - .... .- -. -.- ... ", 24);
+
+	close(fd);
+	pass = pass && !remove_device(id);
+	ksft_test_result(pass, "compression\n");
+}
+
+static void test_tty(void)
+{
+	const char *morse_code = "... --- ... \n";
+	int fd = open(TTY_DEVICE, O_RDWR | O_NOCTTY);
+	int pass;
+
+	if (fd < 0) {
+		ksft_test_result_skip("tty: %s is not available\n", TTY_DEVICE);
+		return;
+	}
+
+	/* The line discipline receives the morse code of each written line */
+	pass = write(fd, "SOS\n", 4) == 4;
+	pass = pass && !read_expected(fd, morse_code, strlen(morse_code));
+
+	/* A character split across writes is completed by the next write */
+	pass = pass && write(fd, "\xc3", 1) == 1 && write(fd, "\xa4\n", 2) == 2;
+	pass = pass && !read_expected(fd, ".-.- \n", 6);
+
+	close(fd);
+	ksft_test_result(pass, "tty\n");
+}
+
+static void test_ldisc(void)
+{
+	const char *morse_code = "... --- ... \n";
+	const char *cut_numbers = "..... -. -.    - - .- \n";
+	static char text[RECEIVE_BUFFER_SIZE + 512];
+	static char long_morse_code[3 * sizeof(text)];
+	int ldisc = N_MORSE;
+	uint32_t expand = 1;
+	int unlock = 0;
+	int master = open(PTY_MASTER, O_RDWR | O_NOCTTY);
+	int slave = -1;
+	size_t i;
+	int pass;
+
+	if (master >= 0 && !ioctl(master, TIOCSPTLCK, &unlock))
+		slave = ioctl(master, TIOCGPTPEER, O_RDWR | O_NOCTTY);
+	if (slave < 0 || ioctl(slave, TIOCSETD, &ldisc)) {
+		ksft_test_result_skip("ldisc: N_MORSE can't be attached to a pty\n");
+		if (slave >= 0)
+			close(slave);
+		if (master >= 0)
+			close(master);
+		return;
+	}
+
+	/* Text written into the slave leaves the master as morse code */
+	pass = write(slave, "SOS\n", 4) == 4;
+	pass = pass && !read_expected(master, morse_code, strlen(morse_code));
+
+	/* Morse code written into the master arrives at the slave as text */
+	pass = pass && write(master, ".- -... \n", 9) == 9;
+	pass = pass && !read_expected(slave, "AB\n", 3);
+
+	/* Text exceeding the receive buffer is kept until it is read, not dropped */
+	memset(text, 'I', sizeof(text));
+	for (i = 0; i < sizeof(text); i++)
+		memcpy(long_morse_code + 3 * i, ".. ", 3);
+	pass = pass && write(master, long_morse_code, sizeof(long_morse_code)) ==
+			       sizeof(long_morse_code);
+	pass = pass && !read_expected(slave, text, sizeof(text));
+
+	/* Received cut numbers are expanded back into digits, if enabled */
+	pass = pass && !ioctl(slave, IOCTL_SET_EXPAND_CUT_NUMBERS, &expand);
+	pass = pass && write(master, cut_numbers, strlen(cut_numbers)) == strlen(cut_numbers);
+	pass = pass && !read_expected(slave, "599 001\n", 8);
+
+	close(slave);
+	close(master);
+	ksft_test_result(pass, "ldisc\n");
+}
+
+static void test_exclusive_access(void)
+{
+	int id = create_device();
+	int reader = open_device(id, O_RDONLY);
+	int writer = open_device(id, O_WRONLY);
+	int pass = reader >= 0 && writer >= 0;
+
+	pass = pass && open_device(id, O_RDONLY) < 0 && errno == EACCES;
+	pass = pass && open_device(id, O_WRONLY) < 0 && errno == EACCES;
+	pass = pass && open_device(id, O_RDWR) < 0 && errno == EACCES;
+
+	close(reader);
+	close(writer);
+	pass = pass && !remove_device(id);
+	ksft_test_result(pass, "exclusive_access\n");
+}
+
+static void test_reader_blocks_until_write(void)
+{
+	int id = create_device();
+	int fd = open_device(id, O_RDWR);
+	int pass = fd >= 0;
+	pid_t child;
+
+	child = pass ? fork() : -1;
+	if (child == 0)
+		_exit(read_expected(fd, ". ", 2) ? 1 : 0);
+
+	pass = child > 0 && !wait_for_block(fd, 0) && child_running(child);
+	pass = pass && write(fd, "E", 1) == 1;
+	pass = child > 0 && wait_for_child(child) == 0 && pass;
+
+	close(fd);
+	pass = pass && !remove_device(id);
+	ksft_test_result(pass, "reader_blocks_until_write\n");
+}
+
+static void test_writer_blocks_until_read(void)
+{
+	char text[BUFFER_SIZE];
+	char morse_code[2 * BUFFER_SIZE];
+	int id = create_device();
+	int fd = open_device(id, O_RDWR);
+	int pass = fd >= 0;
+	pid_t child;
+	size_t i;
+
+	/* Each E is converted to 2 bytes, so the text does not fit into the device */
+	memset(text, 'E', sizeof(text));
+	for (i = 0; i < sizeof(morse_code); i += 2)
+		memcpy(morse_code + i, ". ", 2);
+
+	child = pass ? fork() : -1;
+	if (child == 0)
+		_exit(write(fd, text, sizeof(text)) == (ssize_t)sizeof(text) ? 0 : 1);
+
+	pass = child > 0 && !wait_for_block(fd, 1) && child_running(child);
+	pass = pass && !read_expected(fd, morse_code, sizeof(morse_code));
+	pass = child > 0 && wait_for_child(child) == 0 && pass;
+
+	close(fd);
+	pass = pass && !remove_device(id);
+	ksft_test_result(pass, "writer_blocks_until_read\n");
+}
+
+static void test_read_interrupted(void)
+{
+	struct device_stats stats = { 0 };
+	int id = create_device();
+	int fd = open_device(id, O_RDWR);
+	int pass = fd >= 0;
+	pid_t child;
+
+	child = pass ? fork() : -1;
+	if (child == 0) {
+		char buffer[BUFFER_SIZE];
+		struct sigaction action = { .sa_handler = on_signal };
+
+		/* Without SA_RESTART, the blocked read returns on the signal */
+		sigaction(SIGUSR1, &action, NULL);
+		_exit(read(fd, buffer, sizeof(buffer)) == 0 ? 0 : 1);
+	}
+
+	/* An interrupted read returns 0 bytes and is counted in the statistics */
+	pass = child > 0 && !wait_for_block(fd, 0) && !kill(child, SIGUSR1);
+	pass = child > 0 && wait_for_child(child) == 0 && pass;
+	pass = pass && !ioctl(fd, IOCTL_GET_STATS, &stats) && stats.interrupts == 1;
+
+	close(fd);
+	pass = pass && !remove_device(id);
+	ksft_test_result(pass, "read_interrupted\n");
+}
+
+static void test_write_interrupted(void)
+{
+	char text[BUFFER_SIZE];
+	struct device_stats stats = { 0 };
+	int id = create_device();
+	int fd = open_device(id, O_RDWR);
+	int pass = fd >= 0;
+	pid_t child;
+
+	memset(text, 'E', sizeof(text));
+
+	child = pass ? fork() : -1;
+	if (child == 0) {
+		struct sigaction action = { .sa_handler = on_signal };
+		ssize_t bytes;
+
+		/* Without SA_RESTART, the blocked write returns on the signal */
+		sigaction(SIGUSR1, &action, NULL);
+		bytes = write(fd, text, sizeof(text));
+		_exit(bytes > 0 && bytes < (ssize_t)sizeof(text) ? 0 : 1);
+	}
+
+	/* An interrupted write returns the number of bytes stored before it blocked */
+	pass = child > 0 && !wait_for_block(fd, 1) && !kill(child, SIGUSR1);
+	pass = child > 0 && wait_for_child(child) == 0 && pass;
+	pass = pass && !ioctl(fd, IOCTL_GET_STATS, &stats) && stats.interrupts == 1;
+
+	close(fd);
+	pass = pass && !remove_device(id);
+	ksft_test_result(pass, "write_interrupted\n");
+}
+
+static void test_session_full(void)
+{
+	struct device_config config = { .private_sessions = 1 };
+	char text[BUFFER_SIZE];
+	char morse_code[BUFFER_SIZE];
+	ssize_t first, second;
+	size_t i;
+	int fd = -1;
+	int pass;
+
+	memset(text, 'e', sizeof(text));
+	for (i = 0; i < sizeof(morse_code); i += 2)
+		memcpy(morse_code + i, ". ", 2);
+
+	pass = ioctl(control, IOCTL_CREATE_DEVICE, &config) >= 0;
+	if (pass)
+		fd = open_device(config.id, O_RDWR);
+	pass = pass && fd >= 0;
+
+	/* Writes into a full session return short counts or EAGAIN instead of blocking */
+	first = pass ? write(fd, text, sizeof(text)) : -1;
+	second = first > 0 ? write(fd, text, sizeof(text)) : -1;
+	pass = pass && first > 0 && second > 0 && 2 * (first + second) <= BUFFER_SIZE;
+	pass = pass && write(fd, text, sizeof(text)) < 0 && errno == EAGAIN;
+
+	/* Reading the morse code back makes space again */
+	pass = pass && !read_expected(fd, morse_code, 2 * (first + second));
+	pass = pass && write(fd, "e", 1) == 1 && !read_expected(fd, ". ", 2);
+
+	if (fd >= 0)
+		close(fd);
+	pass = pass && !remove_device(config.id);
+	ksft_test_result(pass, "session_full\n");
+}
+
+static void test_overwrite_mid_letter(void)
+{
+	struct device_config config = { .overwrite = 1 };
+	char text[BUFFER_SIZE];
+	char buffer[2 * BUFFER_SIZE];
+	ssize_t bytes = -1;
+	ssize_t i;
+	int fd = -1;
+	int pass;
+
+	memset(text, 'e', sizeof(text));
+
+	pass = ioctl(control, IOCTL_CREATE_DEVICE, &config) >= 0;
+	if (pass)
+		fd = open_device(config.id, O_RDWR);
+	pass = pass && fd >= 0;
+
+	/* Stop reading in the middle of the letter B */
+	pass = pass && write(fd, "B", 1) == 1 && !read_expected(fd, "-.", 2);
+
+	/* Overwriting B keeps its unread rest, followed by whole letters only */
+	pass = pass && write(fd, text, sizeof(text)) == sizeof(text);
+	if (pass)
+		bytes = read(fd, buffer, sizeof(buffer));
+	pass = pass && bytes > 3 && (bytes - 3) % 2 == 0 && !memcmp(buffer, ".. ", 3);
+	for (i = 3; pass && i < bytes; i += 2)
+		pass = !memcmp(buffer + i, ". ", 2);
+
+	if (fd >= 0)
+		close(fd);
+	pass = pass && !remove_device(config.id);
+	ksft_test_result(pass, "overwrite_mid_letter\n");
+}
+
+static void test_release(void)
+{
+	int id = create_device();
+	int writer = open_device(id, O_WRONLY);
+	int reader;
+	int pass = writer >= 0;
+
+	/* Morse code is kept after its writer was released */
+	pass = pass && write(writer, "SOS", 3) == 3;
+	close(writer);
+	reader = open_device(id, O_RDONLY);
+	pass = pass && reader >= 0 && !read_expected(reader, "... ", 4);
+
+	/* Unread morse code is kept for the next reader after a reader was released */
+	close(reader);
+	reader = open_device(id, O_RDONLY);
+	pass = pass && reader >= 0 && !read_expected(reader, "--- ... ", 8);
+
+	/* Released devices are accessible again */
+	writer = open_device(id, O_WRONLY);
+	pass = pass && writer >= 0;
+
+	/* Devices can only be removed after all files were released */
+	pass = pass && remove_device(id) == EBUSY;
+	close(reader);
+	close(writer);
+	pass = pass && !remove_device(id);
+	ksft_test_result(pass, "release\n");
+}
+
+int main(void)
+{
+	ksft_print_header();
+
+	control = open(CONTROL_DEVICE, O_RDWR);
+	if (control < 0)
+		ksft_exit_skip("Failed to open %s. Is module text_to_morse loaded?\n",
+			       CONTROL_DEVICE);
+
+	ksft_set_plan(16);
+	alarm(TIMEOUT);
+
+	test_golden_corpus();
+	test_partial_write();
+	test_encodings();
+	test_normalization();
+	test_cut_numbers();
+	test_compression();
+	test_tty();
+	test_ldisc();
+	test_exclusive_access();
+	test_reader_blocks_until_write();
+	test_writer_blocks_until_read();
+	test_read_interrupted();
+	test_write_interrupted();
+	test_session_full();
+	test_overwrite_mid_letter();
+	test_release();
+
+	close(control);
+	if (ksft_get_fail_cnt())
+		ksft_exit_fail();
+	ksft_exit_pass();
+}
@@ -0,0 +1,446 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+//! sysfs attributes of the module and all devices.
+//!
+//! The module directory /sys/kernel/text_to_morse/ contains the following attribute:
+//! * log_level: The current log level, writable. See module log for supported values.
+//!
+//! Misc device registrations offer no way to attach attributes to their struct device.
+//! Instead each device gets a device of class morse, /sys/class/morse/<name>/, which is the
+//! parent of its misc device. So /sys/class/misc/morse!<name>/device links to it and udev
+//! rules match its attributes via ATTRS. It contains the following attributes:
+//! * name: The name of the device node /dev/morse/<name>.
+//! * capacity: Number of bytes the device is able to store.
+//! * used: Number of bytes currently stored.
+//! * bytes_in: Total number of bytes converted into morse code.
+//! * chars_converted: Total number of characters converted into morse code.
+//! * bytes_out: Total number of bytes of morse code read.
+//! * unknown_chars: Total number of characters without morse code representation.
+//! * invalid_utf8: Total number of rejected invalid UTF-8 sequences.
+//! * writer_blocks: Total number of times a writer blocked on a full device.
+//! * reader_blocks: Total number of times a reader blocked on an empty device.
+//! * interrupts: Total number of blocked reads and writes interrupted by a signal.
+//! * alphabet: The alphabet used for conversion.
+//! * symbols: The symbols used in the morse code output (dit, dah).
+//! * wpm: Words per minute readers shall key the morse code at.
+//! * readers: Number of readers accessing the device.
+//! * writers: Number of writers accessing the device.
+//!
+//! Uevents of the class device carry the variables MORSE_NAME, MORSE_ALPHABET and
+//! MORSE_SYMBOLS, the same as the attributes name, alphabet and symbols.
+
+use super::{log, morse, Device};
+use alloc::vec::Vec;
+use core::{
+    ffi::{c_char, c_int, c_void},
+    fmt::{self, Write},
+    mem::MaybeUninit,
+    ptr,
+};
+use kernel::{
+    bindings, c_str, device, fmt, prelude::*, str::CString, sync::Arc, ForeignOwnable, PAGE_SIZE,
+};
+
+// Name of the sysfs directory of the module.
+const NAME: &str = "text_to_morse";
+
+// Lockdep key of the device class. Lockdep requires a static key, its content is unused.
+static mut CLASS_KEY: MaybeUninit<bindings::lock_class_key> = MaybeUninit::uninit();
+
+/// sysfs directory of the module and device class of all devices, removed on drop.
+pub(crate) struct Sysfs {
+    root: *mut bindings::kobject,             // Directory of the module
+    log_level: Box<bindings::kobj_attribute>, // Attribute of the module directory
+    class: *mut bindings::class,              // Class of all devices, created if not null
+    attributes: Box<Attributes>,              // Attributes of each device
+}
+
+/// NULL terminated pointers to attribute groups.
+type Groups = [*const bindings::attribute_group; 2];
+
+/// Attributes of each device, referenced by the device class.
+struct Attributes {
+    attrs: Vec<bindings::device_attribute>, // Attributes of each device
+    ptrs: Vec<*mut bindings::attribute>,    // NULL terminated pointers to attributes
+    group: bindings::attribute_group,       // Group of all attributes
+    groups: Groups,                         // NULL terminated pointer to the group
+}
+
+// SAFETY: The raw pointers are only modified on creation and on drop. sysfs itself
+// synchronizes accesses to the kobjects.
+unsafe impl Send for Sysfs {}
+
+// SAFETY: Shared references only pass the raw pointers to sysfs, which synchronizes
+// all accesses.
+unsafe impl Sync for Sysfs {}
+
+impl Sysfs {
+    /// Try to create the sysfs directory of the module and the device class.
+    ///
+    /// # Arguments:
+    /// * module: The module owning the device class.
+    ///
+    /// # Returns:
+    /// On success, an Ok containing the created directory,
+    /// on failure an Err containing one of the following error codes:
+    /// * ENOMEM: The directory or the class must be allocated and this fails.
+    /// * EEXIST: The class exists already.
+    pub(crate) fn try_new(module: &'static ThisModule) -> Result<Self> {
+        let names = [
+            c_str!("name"),
+            c_str!("capacity"),
+            c_str!("used"),
+            c_str!("bytes_in"),
+            c_str!("chars_converted"),
+            c_str!("bytes_out"),
+            c_str!("unknown_chars"),
+            c_str!("invalid_utf8"),
+            c_str!("writer_blocks"),
+            c_str!("reader_blocks"),
+            c_str!("interrupts"),
+            c_str!("alphabet"),
+            c_str!("symbols"),
+            c_str!("wpm"),
+            c_str!("readers"),
+            c_str!("writers"),
+        ];
+
+        // Create attributes. The pointer array must not be created before all attributes
+        // are stored, since growing the attribute Vec would invalidate the pointers.
+        let mut attrs = Vec::try_with_capacity(names.len())?;
+        for name in names {
+            // SAFETY: device_attribute is a plain C struct. All zeroes is a valid value.
+            let mut attr: bindings::device_attribute = unsafe { core::mem::zeroed() };
+            attr.attr.name = name.as_char_ptr();
+            attr.attr.mode = 0o444;
+            attr.show = Some(show);
+            attrs.try_push(attr)?;
+        }
+
+        let mut ptrs = Vec::try_with_capacity(names.len() + 1)?;
+        for attr in attrs.iter_mut() {
+            ptrs.try_push(&mut attr.attr as *mut bindings::attribute)?;
+        }
+        ptrs.try_push(ptr::null_mut())?;
+
+        let mut attributes = Box::try_new(Attributes {
+            attrs,
+            ptrs,
+            // SAFETY: attribute_group is a plain C struct. All zeroes is a valid value.
+            group: unsafe { core::mem::zeroed() },
+            groups: [ptr::null(); 2],
+        })?;
+        attributes.group.attrs = attributes.ptrs.as_mut_ptr();
+        attributes.groups[0] = &attributes.group;
+
+        // SAFETY: kobj_attribute is a plain C struct. All zeroes is a valid value.
+        let mut log_level: bindings::kobj_attribute = unsafe { core::mem::zeroed() };
+        log_level.attr.name = c_str!("log_level").as_char_ptr();
+        log_level.attr.mode = 0o644;
+        log_level.show = Some(show_log_level);
+        log_level.store = Some(store_log_level);
+        let log_level = Box::try_new(log_level)?;
+
+        // Create root directory.
+        let name = CString::try_from_fmt(fmt!("{}", NAME))?;
+
+        // SAFETY: kernel_kobj is initialized on boot and never changes afterwards.
+        // name is copied by kobject_create_and_add.
+        let root =
+            unsafe { bindings::kobject_create_and_add(name.as_char_ptr(), bindings::kernel_kobj) };
+        if root.is_null() {
+            return Err(ENOMEM);
+        }
+
+        let mut sysfs = Self {
+            root,
+            log_level,
+            class: ptr::null_mut(),
+            attributes,
+        };
+
+        // SAFETY: root is a valid kobject. The attribute lives as long as the directory.
+        let ret = unsafe { bindings::sysfs_create_file(sysfs.root, &sysfs.log_level.attr) };
+        if ret != 0 {
+            return Err(Error::from_kernel_errno(ret));
+        }
+
+        // Same as class_create. The name is static, the key is never accessed by Rust.
+        // SAFETY: FFI call, the class is destroyed on drop.
+        let class = unsafe {
+            bindings::__class_create(
+                module.as_ptr(),
+                c_str!("morse").as_char_ptr(),
+                ptr::addr_of_mut!(CLASS_KEY).cast(),
+            )
+        };
+        // SAFETY: Only checks the returned pointer.
+        if unsafe { bindings::IS_ERR(class as *const c_void) } {
+            // SAFETY: class is an error pointer.
+            let errno = unsafe { bindings::PTR_ERR(class as *const c_void) };
+            return Err(Error::from_kernel_errno(errno as c_int));
+        }
+
+        // SAFETY: No device of the class exists yet. The groups live as long as the class.
+        unsafe {
+            (*class).dev_groups = sysfs.attributes.groups.as_mut_ptr();
+            (*class).dev_uevent = Some(uevent);
+        }
+        sysfs.class = class;
+        Ok(sysfs)
+    }
+
+    /// Try to create the class device of a device.
+    ///
+    /// # Arguments:
+    /// * device: The device to create a class device for.
+    /// * name: The name of the class device, the same as the name of the device node.
+    ///
+    /// # Returns:
+    /// On success, an Ok containing the created class device,
+    /// on failure an Err containing one of the following error codes:
+    /// * ENOMEM: The class device must be allocated and this fails.
+    /// * EEXIST: A class device with the same name exists already.
+    ///
+    /// # Notes:
+    /// The class device must be dropped before the Sysfs object it was created by.
+    pub(crate) fn try_add_device(&self, device: &Arc<Device>, name: &CStr) -> Result<SysfsDir> {
+        // The class device holds a reference to the device, released on drop.
+        let data = device.clone().into_foreign();
+
+        // SAFETY: The class is valid until it is destroyed on drop. name is copied.
+        let dev = unsafe {
+            bindings::device_create(
+                self.class,
+                ptr::null_mut(),
+                0,
+                data as *mut c_void,
+                c_str!("%s").as_char_ptr(),
+                name.as_char_ptr(),
+            )
+        };
+        // SAFETY: Only checks the returned pointer.
+        if unsafe { bindings::IS_ERR(dev as *const c_void) } {
+            // SAFETY: data was returned by into_foreign above and is not used anymore.
+            drop(unsafe { Arc::<Device>::from_foreign(data) });
+            // SAFETY: dev is an error pointer.
+            let errno = unsafe { bindings::PTR_ERR(dev as *const c_void) };
+            return Err(Error::from_kernel_errno(errno as c_int));
+        }
+        Ok(SysfsDir { dev, data })
+    }
+}
+
+impl Drop for Sysfs {
+    fn drop(&mut self) {
+        // SAFETY: class and root were created on creation and are released only once.
+        // Releasing a kobject removes its directory and all contained attributes.
+        unsafe {
+            if !self.class.is_null() {
+                bindings::class_destroy(self.class);
+            }
+            bindings::kobject_put(self.root);
+        }
+    }
+}
+
+/// Class device of a device, removed on drop.
+pub(crate) struct SysfsDir {
+    dev: *mut bindings::device, // Class device containing all device attributes
+    data: *const c_void,        // Reference to the device, stored as driver data
+}
+
+// SAFETY: The raw pointers are only accessed on creation and on drop.
+unsafe impl Send for SysfsDir {}
+
+// SAFETY: SysfsDir offers no methods modifying the raw pointers.
+unsafe impl Sync for SysfsDir {}
+
+impl SysfsDir {
+    /// Get the class device, e.g. as parent of the device node.
+    pub(crate) fn device(&self) -> device::Device {
+        // SAFETY: dev is valid until drop. The returned Device holds its own reference.
+        unsafe { device::Device::new(self.dev) }
+    }
+}
+
+impl Drop for SysfsDir {
+    fn drop(&mut self) {
+        // SAFETY: dev was created by device_create and is unregistered only once.
+        // Unregistering waits until all running attribute callbacks returned, so the
+        // reference to the device is not used anymore.
+        unsafe {
+            bindings::device_unregister(self.dev);
+            drop(Arc::<Device>::from_foreign(self.data));
+        }
+    }
+}
+
+/// Writer formatting text into a sysfs or configfs attribute buffer.
+pub(crate) struct PageWriter {
+    pub(crate) buf: *mut u8, // Buffer provided by sysfs, PAGE_SIZE bytes large
+    pub(crate) pos: usize,   // Number of bytes written into buffer
+}
+
+impl Write for PageWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let len = s.len().min(PAGE_SIZE - self.pos);
+
+        // SAFETY: buf points to PAGE_SIZE bytes and pos + len never exceeds PAGE_SIZE.
+        unsafe { ptr::copy_nonoverlapping(s.as_ptr(), self.buf.add(self.pos), len) };
+        self.pos += len;
+        Ok(())
+    }
+}
+
+/// Show callback of all device attributes
+///
+/// # Arguments:
+/// * dev: The class device of the device the attribute belongs to.
+/// * attr: The attribute to show.
+/// * buf: Buffer of PAGE_SIZE bytes to write the attribute value into.
+///
+/// # Returns:
+/// On success, the number of bytes written into buf, on failure a negative error code:
+/// * EINVAL: The attribute is unknown.
+unsafe extern "C" fn show(
+    dev: *mut bindings::device,
+    attr: *mut bindings::device_attribute,
+    buf: *mut c_char,
+) -> bindings::ssize_t {
+    // SAFETY: sysfs passes valid attributes with NUL terminated names. Class devices store
+    // a reference to their device as driver data, see Sysfs::try_add_device.
+    let (device, name) = unsafe {
+        (
+            Arc::<Device>::borrow((*dev).driver_data),
+            CStr::from_char_ptr((*attr).attr.name),
+        )
+    };
+
+    let mut page = PageWriter {
+        buf: buf as *mut u8,
+        pos: 0,
+    };
+    let inner = device.inner.lock();
+    let _ = match name.as_bytes() {
+        // SAFETY: Devices have a NUL terminated name.
+        b"name" => writeln!(page, "{}", unsafe { CStr::from_char_ptr((*dev).kobj.name) }),
+        b"capacity" => writeln!(page, "{}", inner.queue.size()),
+        b"used" => writeln!(page, "{}", inner.queue.len()),
+        b"bytes_in" => writeln!(page, "{}", inner.stats.bytes_in),
+        b"chars_converted" => writeln!(page, "{}", inner.stats.chars_converted),
+        b"bytes_out" => writeln!(page, "{}", inner.stats.bytes_out),
+        b"unknown_chars" => writeln!(page, "{}", inner.stats.unknown_chars),
+        b"invalid_utf8" => writeln!(page, "{}", inner.stats.invalid_utf8),
+        b"writer_blocks" => writeln!(page, "{}", inner.stats.writer_blocks),
+        b"reader_blocks" => writeln!(page, "{}", inner.stats.reader_blocks),
+        b"interrupts" => writeln!(page, "{}", inner.stats.interrupts),
+        b"alphabet" => writeln!(page, "{}", morse::ALPHABET),
+        b"symbols" => {
+            let [dit, dah] = inner.options.symbols;
+            writeln!(page, "{}{}", dit as char, dah as char)
+        }
+        b"wpm" => writeln!(page, "{}", inner.options.wpm),
+        b"readers" => writeln!(page, "{}", inner.readers.len()),
+        b"writers" => writeln!(page, "{}", inner.writers),
+        _ => return EINVAL.to_kernel_errno() as bindings::ssize_t,
+    };
+    page.pos as bindings::ssize_t
+}
+
+/// Uevent callback of all class devices
+///
+/// # Arguments:
+/// * dev: The class device of the device the uevent is sent for.
+/// * env: The variables of the uevent.
+///
+/// # Returns:
+/// 0 on success, otherwise a negative error code: ENOMEM if env is full.
+unsafe extern "C" fn uevent(
+    dev: *mut bindings::device,
+    env: *mut bindings::kobj_uevent_env,
+) -> c_int {
+    // SAFETY: Class devices store a reference to their device as driver data, before they
+    // are added. See Sysfs::try_add_device.
+    let device = unsafe { Arc::<Device>::borrow((*dev).driver_data) };
+    let (alphabet, symbols) = (morse::ALPHABET, device.inner.lock().options.symbols);
+    // SAFETY: The driver core passes a valid device with a NUL terminated name and a valid
+    // env. Each format only consumes the given arguments.
+    unsafe {
+        let ret =
+            bindings::add_uevent_var(env, c_str!("MORSE_NAME=%s").as_char_ptr(), (*dev).kobj.name);
+        if ret != 0 {
+            return ret;
+        }
+        let ret = bindings::add_uevent_var(
+            env,
+            c_str!("MORSE_ALPHABET=%.*s").as_char_ptr(),
+            alphabet.len() as c_int,
+            alphabet.as_ptr(),
+        );
+        if ret != 0 {
+            return ret;
+        }
+        bindings::add_uevent_var(
+            env,
+            c_str!("MORSE_SYMBOLS=%.*s").as_char_ptr(),
+            symbols.len() as c_int,
+            symbols.as_ptr(),
+        )
+    }
+}
+
+/// Show callback of the log_level attribute
+///
+/// # Arguments:
+/// * _kobj: The module directory.
+/// * _attr: The log_level attribute.
+/// * buf: Buffer of PAGE_SIZE bytes to write the log level into.
+///
+/// # Returns:
+/// The number of bytes written into buf.
+unsafe extern "C" fn show_log_level(
+    _kobj: *mut bindings::kobject,
+    _attr: *mut bindings::kobj_attribute,
+    buf: *mut c_char,
+) -> bindings::ssize_t {
+    let mut page = PageWriter {
+        buf: buf as *mut u8,
+        pos: 0,
+    };
+    let _ = writeln!(page, "{}", log::level());
+    page.pos as bindings::ssize_t
+}
+
+/// Store callback of the log_level attribute
+///
+/// # Arguments:
+/// * _kobj: The module directory.
+/// * _attr: The log_level attribute.
+/// * buf: Buffer containing the new log level as decimal number.
+/// * count: The number of bytes in buf.
+///
+/// # Returns:
+/// On success, count, on failure a negative error code:
+/// * EINVAL: buf contains no valid log level.
+unsafe extern "C" fn store_log_level(
+    _kobj: *mut bindings::kobject,
+    _attr: *mut bindings::kobj_attribute,
+    buf: *const c_char,
+    count: usize,
+) -> bindings::ssize_t {
+    // SAFETY: sysfs passes a buffer containing count bytes.
+    let bytes = unsafe { core::slice::from_raw_parts(buf as *const u8, count) };
+    let level = core::str::from_utf8(bytes)
+        .ok()
+        .and_then(|level| level.trim().parse::<u32>().ok());
+
+    match level {
+        Some(level) => {
+            log::set_level(level);
+            count as bindings::ssize_t
+        }
+        None => EINVAL.to_kernel_errno() as bindings::ssize_t,
+    }
+}
@@ -0,0 +1,57 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+/*
+ * Definition of all tracepoints and wrappers to emit them from Rust.
+ *
+ * The trace_<event> functions generated by TRACE_EVENT are static inline, so each event
+ * gets a wrapper with C linkage. The wrappers are declared in trace.rs.
+ */
+
+#define CREATE_TRACE_POINTS
+#include "trace.h"
+
+void text_to_morse_trace_char_converted(u16 device, u32 codepoint, const char *code, size_t len)
+{
+	trace_char_converted(device, codepoint, code, len);
+}
+
+void text_to_morse_trace_queue_full(u16 device)
+{
+	trace_queue_full(device);
+}
+
+void text_to_morse_trace_queue_empty(u16 device)
+{
+	trace_queue_empty(device);
+}
+
+void text_to_morse_trace_reader_wait(u16 device)
+{
+	trace_reader_wait(device);
+}
+
+void text_to_morse_trace_reader_wakeup(u16 device)
+{
+	trace_reader_wakeup(device);
+}
+
+void text_to_morse_trace_writer_wait(u16 device)
+{
+	trace_writer_wait(device);
+}
+
+void text_to_morse_trace_writer_wakeup(u16 device)
+{
+	trace_writer_wakeup(device);
+}
+
+void text_to_morse_trace_open(u16 device, u32 mode)
+{
+	trace_open(device, mode);
+}
+
+void text_to_morse_trace_release(u16 device, u32 mode)
+{
+	trace_release(device, mode);
+}
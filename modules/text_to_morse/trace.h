@@ -0,0 +1,85 @@
+/* SPDX-License-Identifier: Dual MPL/GPL */
+/* Author: Simon Brummer <simon.brummer@posteo.de> */
+
+/*
+ * Tracepoints of module text_to_morse.
+ *
+ * Tracepoints can only be defined via the C macros below, so the Rust part of the module
+ * emits them via the wrapper functions defined in trace.c. See trace.rs for their usage.
+ * All events are available under /sys/kernel/tracing/events/text_to_morse/.
+ */
+
+#undef TRACE_SYSTEM
+#define TRACE_SYSTEM text_to_morse
+
+#if !defined(_TEXT_TO_MORSE_TRACE_H) || defined(TRACE_HEADER_MULTI_READ)
+#define _TEXT_TO_MORSE_TRACE_H
+
+#include <linux/fcntl.h>
+#include <linux/tracepoint.h>
+
+TRACE_EVENT(char_converted,
+	TP_PROTO(u16 device, u32 codepoint, const char *code, size_t len),
+	TP_ARGS(device, codepoint, code, len),
+	TP_STRUCT__entry(
+		__field(u16, device)
+		__field(u32, codepoint)
+		__string_len(code, code, len)
+	),
+	TP_fast_assign(
+		__entry->device = device;
+		__entry->codepoint = codepoint;
+		__assign_str_len(code, code, len);
+	),
+	TP_printk("device=%u codepoint=U+%04X code=\"%s\"",
+		  __entry->device, __entry->codepoint, __get_str(code))
+);
+
+DECLARE_EVENT_CLASS(device_event,
+	TP_PROTO(u16 device),
+	TP_ARGS(device),
+	TP_STRUCT__entry(
+		__field(u16, device)
+	),
+	TP_fast_assign(
+		__entry->device = device;
+	),
+	TP_printk("device=%u", __entry->device)
+);
+
+DEFINE_EVENT(device_event, queue_full, TP_PROTO(u16 device), TP_ARGS(device));
+DEFINE_EVENT(device_event, queue_empty, TP_PROTO(u16 device), TP_ARGS(device));
+DEFINE_EVENT(device_event, reader_wait, TP_PROTO(u16 device), TP_ARGS(device));
+DEFINE_EVENT(device_event, reader_wakeup, TP_PROTO(u16 device), TP_ARGS(device));
+DEFINE_EVENT(device_event, writer_wait, TP_PROTO(u16 device), TP_ARGS(device));
+DEFINE_EVENT(device_event, writer_wakeup, TP_PROTO(u16 device), TP_ARGS(device));
+
+DECLARE_EVENT_CLASS(access_event,
+	TP_PROTO(u16 device, u32 mode),
+	TP_ARGS(device, mode),
+	TP_STRUCT__entry(
+		__field(u16, device)
+		__field(u32, mode)
+	),
+	TP_fast_assign(
+		__entry->device = device;
+		__entry->mode = mode;
+	),
+	TP_printk("device=%u mode=%s", __entry->device,
+		  __print_symbolic(__entry->mode,
+				   { O_RDONLY, "O_RDONLY" },
+				   { O_WRONLY, "O_WRONLY" },
+				   { O_RDWR, "O_RDWR" }))
+);
+
+DEFINE_EVENT(access_event, open, TP_PROTO(u16 device, u32 mode), TP_ARGS(device, mode));
+DEFINE_EVENT(access_event, release, TP_PROTO(u16 device, u32 mode), TP_ARGS(device, mode));
+
+#endif /* _TEXT_TO_MORSE_TRACE_H */
+
+/* This part must be outside the include guard */
+#undef TRACE_INCLUDE_PATH
+#define TRACE_INCLUDE_PATH .
+#undef TRACE_INCLUDE_FILE
+#define TRACE_INCLUDE_FILE trace
+#include <trace/define_trace.h>
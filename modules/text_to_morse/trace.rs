@@ -0,0 +1,102 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+//! Tracepoints of all devices.
+//!
+//! The tracepoints are defined in trace.h, since they can only be defined in C. This module
+//! wraps the C functions emitting them. All events are available under
+//! /sys/kernel/tracing/events/text_to_morse/ and are usable via perf and trace-cmd, e.g.
+//! `trace-cmd record -e text_to_morse`. Disabled tracepoints cost only a function call.
+
+use core::ffi::c_char;
+
+extern "C" {
+    fn text_to_morse_trace_char_converted(
+        device: u16,
+        codepoint: u32,
+        code: *const c_char,
+        len: usize,
+    );
+    fn text_to_morse_trace_queue_full(device: u16);
+    fn text_to_morse_trace_queue_empty(device: u16);
+    fn text_to_morse_trace_reader_wait(device: u16);
+    fn text_to_morse_trace_reader_wakeup(device: u16);
+    fn text_to_morse_trace_writer_wait(device: u16);
+    fn text_to_morse_trace_writer_wakeup(device: u16);
+    fn text_to_morse_trace_open(device: u16, mode: u32);
+    fn text_to_morse_trace_release(device: u16, mode: u32);
+}
+
+/// Trace the conversion of a character
+///
+/// # Arguments:
+/// * device: The id of the device converting the character.
+/// * char: The converted character.
+/// * code: The morse code the character was converted into.
+pub(crate) fn char_converted(device: u16, char: char, code: &str) {
+    // SAFETY: code is valid for code.len() bytes. It is copied into the trace buffer.
+    unsafe {
+        text_to_morse_trace_char_converted(
+            device,
+            char as u32,
+            code.as_ptr() as *const c_char,
+            code.len(),
+        )
+    };
+}
+
+/// Trace a queue becoming full.
+pub(crate) fn queue_full(device: u16) {
+    // SAFETY: Plain function call without pointers.
+    unsafe { text_to_morse_trace_queue_full(device) };
+}
+
+/// Trace a queue becoming empty.
+pub(crate) fn queue_empty(device: u16) {
+    // SAFETY: Plain function call without pointers.
+    unsafe { text_to_morse_trace_queue_empty(device) };
+}
+
+/// Trace a reader going to sleep until morse code is available.
+pub(crate) fn reader_wait(device: u16) {
+    // SAFETY: Plain function call without pointers.
+    unsafe { text_to_morse_trace_reader_wait(device) };
+}
+
+/// Trace a reader waking up.
+pub(crate) fn reader_wakeup(device: u16) {
+    // SAFETY: Plain function call without pointers.
+    unsafe { text_to_morse_trace_reader_wakeup(device) };
+}
+
+/// Trace a writer going to sleep until space is available.
+pub(crate) fn writer_wait(device: u16) {
+    // SAFETY: Plain function call without pointers.
+    unsafe { text_to_morse_trace_writer_wait(device) };
+}
+
+/// Trace a writer waking up.
+pub(crate) fn writer_wakeup(device: u16) {
+    // SAFETY: Plain function call without pointers.
+    unsafe { text_to_morse_trace_writer_wakeup(device) };
+}
+
+/// Trace a successful open
+///
+/// # Arguments:
+/// * device: The id of the opened device.
+/// * mode: The access mode of the open, one of O_RDONLY, O_WRONLY or O_RDWR.
+pub(crate) fn open(device: u16, mode: u32) {
+    // SAFETY: Plain function call without pointers.
+    unsafe { text_to_morse_trace_open(device, mode) };
+}
+
+/// Trace a release
+///
+/// # Arguments:
+/// * device: The id of the released device.
+/// * mode: The access mode of the released file, one of O_RDONLY, O_WRONLY or O_RDWR.
+pub(crate) fn release(device: u16, mode: u32) {
+    // SAFETY: Plain function call without pointers.
+    unsafe { text_to_morse_trace_release(device, mode) };
+}
@@ -0,0 +1,449 @@
+// SPDX-License-Identifier: Dual MPL/GPL
+// Author: Simon Brummer <simon.brummer@posteo.de>
+
+//! TTY driver exposing the conversion as serial ports.
+//!
+//! Each port /dev/ttyMORSE<n> loops written text back as morse code: Text written into the
+//! port is converted into morse code, which is received on the same port, as if a serial
+//! line echoed it. The received morse code passes the line discipline of the port like any
+//! serial input, e.g. n_tty delivers it line by line in canonical mode. The number of ports
+//! is given by module parameter TTYS.
+//!
+//! Text is decoded as UTF-8 and handled according to module parameters UTF8_POLICY and
+//! NORMALIZATION. Compression uses the default abbreviations. A character split across
+//! writes is completed by the next write.
+//!
+//! Echo and the mapping of newlines to CR/NL are disabled by default, since the morse code
+//! would be converted again. Baud rate and other line settings are accepted, but have no
+//! effect, since conversion is not timed.
+//!
+//! The tty core may write from atomic context. So writes neither sleep nor allocate: The
+//! conversion state and a morse code buffer of CHUNK_SIZE bytes are allocated per port on
+//! creation and protected by a spinlock.
+
+use super::{
+    compress::{Compressor, DEFAULT_DICTIONARY},
+    convert::{convert, Stop},
+    morse::MAX_MORSE_CODE_LEN,
+    normalize::{CutNumbers, Normalizer, Stages, State},
+    utf8::{DecodeError, Decoder, Policy},
+    CHUNK_SIZE, NORMALIZATION, UTF8_POLICY,
+};
+use alloc::vec::Vec;
+use core::{
+    ffi::{c_char, c_int, c_uint},
+    mem::MaybeUninit,
+    ptr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use kernel::{bindings, c_str, container_of, prelude::*, sync::SpinLock};
+
+// Maximum number of bytes of a character truncated at the end of a write.
+const MAX_PENDING_LEN: usize = 3;
+
+/// Conversion state of a stream of text, carried over between writes.
+#[derive(Default)]
+pub(crate) struct Converter {
+    pending: [u8; MAX_PENDING_LEN], // Bytes of a character truncated by the last write
+    pending_len: usize,             // Number of pending bytes
+    normalization: State,           // Normalization state after the converted text
+    word: bool,                     // Flag if the converted text ends within a word
+}
+
+impl Converter {
+    /// Convert text into morse code
+    ///
+    /// # Arguments:
+    /// * bytes: The UTF-8 encoded text to convert.
+    /// * space: The maximum number of bytes of morse code to store.
+    /// * morse_code: Vector to append the morse code to. Nothing is allocated, if its spare
+    ///   capacity covers space.
+    ///
+    /// # Returns:
+    /// On success, an Ok containing the number of bytes of text consumed. It is less than
+    /// bytes.len() if the space is exhausted. On failure, an Err containing one of the
+    /// following error codes:
+    /// * EINVAL: The text starts with an invalid sequence and the UTF-8 policy is Fail.
+    /// * ENOMEM: The morse code must be stored and this fails.
+    ///
+    /// # Notes:
+    /// A character truncated at the end of the text is consumed and kept until the next call.
+    pub(crate) fn convert(
+        &mut self,
+        bytes: &[u8],
+        space: usize,
+        morse_code: &mut Vec<u8>,
+    ) -> Result<usize> {
+        let policy = Policy::from_id(*UTF8_POLICY.read()).unwrap_or(Policy::Fail);
+        let stages = Stages::from_bits(*NORMALIZATION.read()).unwrap_or_default();
+        let mut normalizer = Normalizer::new(stages, CutNumbers::default(), self.normalization);
+        let dictionary = stages
+            .contains(Stages::COMPRESS)
+            .then_some(&DEFAULT_DICTIONARY);
+        let mut compressor = Compressor::new(dictionary, self.word);
+
+        let pending_len = self.pending_len;
+        let input = || self.pending[..pending_len].iter().chain(bytes).copied();
+        let mut decoder = Decoder::new();
+        let mut next = input();
+        let read_char = || decoder.decode(|| next.next());
+        let store = |_, code: &str| morse_code.try_extend_from_slice(code.as_bytes());
+        let conversion = convert(
+            space,
+            policy,
+            &mut normalizer,
+            &mut compressor,
+            read_char,
+            store,
+        )?;
+        let bytes_read = conversion.bytes_read;
+
+        // Pending bytes not consumed yet and a truncated character are kept.
+        let kept = match conversion.stop {
+            Stop::Invalid(DecodeError::Truncated) => pending_len + bytes.len() - bytes_read,
+            Stop::Invalid(DecodeError::Invalid) if bytes_read == 0 => {
+                // Discard a truncated character the text does not complete.
+                self.pending_len = 0;
+                return Err(EINVAL);
+            }
+            _ => pending_len.saturating_sub(bytes_read),
+        };
+        let mut pending = [0; MAX_PENDING_LEN];
+        for (dst, src) in pending.iter_mut().zip(input().skip(bytes_read).take(kept)) {
+            *dst = src;
+        }
+        self.pending = pending;
+        self.pending_len = kept;
+        self.normalization = normalizer.state();
+        self.word = compressor.word();
+        Ok((bytes_read + kept).saturating_sub(pending_len))
+    }
+}
+
+/// TTY driver of the module, unregistered on drop.
+pub(crate) struct Tty {
+    driver: *mut bindings::tty_driver, // Allocated driver
+    ports: Box<Ports>,                 // Ports of the driver
+    registered: bool,                  // Flag if the driver is registered
+    devices: usize,                    // Number of registered port devices
+}
+
+// SAFETY: The driver is only modified on creation and on drop. The tty core synchronizes
+// all accesses to it.
+unsafe impl Send for Tty {}
+
+// SAFETY: Tty offers no methods accessing the driver.
+unsafe impl Sync for Tty {}
+
+/// All data structures of the driver, referenced by the tty core.
+struct Ports {
+    ops: bindings::tty_operations,           // Operations of the driver
+    port_ops: bindings::tty_port_operations, // Operations of all ports, none are needed
+    ports: Vec<Port>,                        // Ports, indexed by the tty index
+}
+
+/// A port converting written text into received morse code.
+struct Port {
+    port: bindings::tty_port,   // tty port, referenced by the tty core
+    state: SpinLock<PortState>, // Conversion state, locked by writes
+    throttled: AtomicBool,      // Flag if the line discipline stopped receiving
+}
+
+/// Conversion state of a port, allocated on creation.
+struct PortState {
+    converter: Converter, // Conversion state of the written text
+    morse_code: Vec<u8>,  // Morse code of the current write, CHUNK_SIZE bytes of capacity
+}
+
+impl Tty {
+    /// Try to register the TTY driver and its ports.
+    ///
+    /// # Arguments:
+    /// * module: The module owning the driver.
+    /// * count: The number of ports.
+    ///
+    /// # Returns:
+    /// On success, an Ok containing the registered driver,
+    /// on failure an Err containing one of the following error codes:
+    /// * ENOMEM: The driver or its ports must be allocated and this fails.
+    /// * EBUSY: No major number is available.
+    pub(crate) fn try_new(module: &'static ThisModule, count: u32) -> Result<Self> {
+        let mut ports = Box::try_new(Ports {
+            // SAFETY: Both are plain C structs of function pointers. All zeroes is valid.
+            ops: unsafe { MaybeUninit::zeroed().assume_init() },
+            port_ops: unsafe { MaybeUninit::zeroed().assume_init() },
+            ports: Vec::try_with_capacity(count as usize)?,
+        })?;
+        for _ in 0..count {
+            let state = PortState {
+                converter: Converter::default(),
+                morse_code: Vec::try_with_capacity(CHUNK_SIZE)?,
+            };
+            ports.ports.try_push(Port {
+                // SAFETY: tty_port is a plain C struct, initialized by tty_port_init below.
+                port: unsafe { MaybeUninit::zeroed().assume_init() },
+                // SAFETY: The spinlock is initialized by spinlock_init below.
+                state: unsafe { SpinLock::new(state) },
+                throttled: AtomicBool::new(false),
+            })?;
+        }
+        ports.ops.open = Some(open);
+        ports.ops.close = Some(close);
+        ports.ops.hangup = Some(hangup);
+        ports.ops.write = Some(write);
+        ports.ops.write_room = Some(write_room);
+        ports.ops.throttle = Some(throttle);
+        ports.ops.unthrottle = Some(unthrottle);
+
+        let flags = bindings::TTY_DRIVER_REAL_RAW | bindings::TTY_DRIVER_DYNAMIC_DEV;
+        // SAFETY: FFI call without pointers besides the module. The driver is released
+        // via tty_driver_kref_put on failure and on drop.
+        let driver = unsafe { bindings::__tty_alloc_driver(count, module.as_ptr(), flags.into()) };
+        // SAFETY: Only checks the returned pointer.
+        if unsafe { bindings::IS_ERR(driver as *const _) } {
+            // SAFETY: driver is an error pointer.
+            let errno = unsafe { bindings::PTR_ERR(driver as *const _) };
+            return Err(Error::from_kernel_errno(errno as c_int));
+        }
+
+        // SAFETY: driver was just allocated. The ports are allocated on the heap, so they
+        // never move and their spinlocks stay pinned. tty_std_termios is only read.
+        unsafe {
+            let d = &mut *driver;
+            d.driver_name = c_str!("text_to_morse").as_char_ptr();
+            d.name = c_str!("ttyMORSE").as_char_ptr();
+            d.type_ = bindings::TTY_DRIVER_TYPE_SERIAL as _;
+            d.subtype = bindings::SERIAL_TYPE_NORMAL as _;
+            d.init_termios = bindings::tty_std_termios;
+            d.init_termios.c_lflag &= !(bindings::ECHO
+                | bindings::ECHOE
+                | bindings::ECHOK
+                | bindings::ECHONL
+                | bindings::ECHOCTL
+                | bindings::ECHOKE);
+            d.init_termios.c_oflag &= !bindings::ONLCR;
+            d.ops = &ports.ops;
+            for port in ports.ports.iter_mut() {
+                bindings::tty_port_init(&mut port.port);
+                port.port.ops = &ports.port_ops;
+                kernel::spinlock_init!(Pin::new_unchecked(&mut port.state), "Port::state");
+            }
+        }
+
+        // From here on, drop unregisters the driver and all registered port devices.
+        let mut tty = Self {
+            driver,
+            ports,
+            registered: false,
+            devices: 0,
+        };
+
+        // SAFETY: The driver is completely initialized.
+        let ret = unsafe { bindings::tty_register_driver(tty.driver) };
+        if ret != 0 {
+            return Err(Error::from_kernel_errno(ret));
+        }
+        tty.registered = true;
+
+        for (index, port) in tty.ports.ports.iter().enumerate() {
+            // SAFETY: The driver is registered and index is below its number of lines.
+            // The device is unregistered on drop.
+            let device = unsafe {
+                bindings::tty_port_register_device(
+                    port.raw(),
+                    tty.driver,
+                    index as c_uint,
+                    ptr::null_mut(),
+                )
+            };
+            // SAFETY: Only checks the returned pointer.
+            if unsafe { bindings::IS_ERR(device as *const _) } {
+                // SAFETY: device is an error pointer.
+                let errno = unsafe { bindings::PTR_ERR(device as *const _) };
+                log_err!("Failed to register ttyMORSE{}.\n", index);
+                return Err(Error::from_kernel_errno(errno as c_int));
+            }
+            tty.devices += 1;
+        }
+        log_info!("Registered {} ttyMORSE ports.\n", count);
+        Ok(tty)
+    }
+}
+
+impl Drop for Tty {
+    fn drop(&mut self) {
+        // SAFETY: Only registered devices and a registered driver are unregistered. An open
+        // port holds a reference to the module, so no port is in use anymore.
+        unsafe {
+            if self.registered {
+                for index in 0..self.devices {
+                    bindings::tty_unregister_device(self.driver, index as c_uint);
+                }
+                bindings::tty_unregister_driver(self.driver);
+            }
+            for port in self.ports.ports.iter() {
+                bindings::tty_port_destroy(port.raw());
+            }
+            // Drops the reference taken by __tty_alloc_driver.
+            bindings::tty_driver_kref_put(self.driver);
+        }
+    }
+}
+
+impl Port {
+    /// Get the port of a tty
+    ///
+    /// # Safety
+    /// tty must be a tty of the driver, so its port is the port of a Port.
+    unsafe fn from_tty<'a>(tty: *mut bindings::tty_struct) -> &'a Port {
+        // SAFETY: Guaranteed by the caller.
+        unsafe { &*container_of!((*tty).port, Port, port) }
+    }
+
+    /// Get the raw port, as expected by the tty core.
+    fn raw(&self) -> *mut bindings::tty_port {
+        &self.port as *const _ as *mut _
+    }
+
+    /// Convert text and receive its morse code
+    ///
+    /// # Arguments:
+    /// * bytes: The UTF-8 encoded text written into the port.
+    ///
+    /// # Returns:
+    /// On success, an Ok containing the number of bytes converted. It is 0 if the receive
+    /// buffer is full. On failure, an Err containing return code EINVAL, see
+    /// Converter::convert. Storing the morse code never fails, since at most CHUNK_SIZE
+    /// bytes are stored into the preallocated buffer.
+    fn write(&self, bytes: &[u8]) -> Result<usize> {
+        // SAFETY: The port was initialized on creation.
+        let space = unsafe { bindings::tty_buffer_space_avail(self.raw()) } as usize;
+        let mut state = self.state.lock();
+        let PortState {
+            converter,
+            morse_code,
+        } = &mut *state;
+        morse_code.clear();
+        let written = converter.convert(bytes, space.min(CHUNK_SIZE), morse_code)?;
+
+        // SAFETY: morse_code is valid for its length. It is copied into the receive buffer.
+        // The lock serializes the insertions of concurrent writes.
+        unsafe {
+            bindings::tty_insert_flip_string_fixed_flag(
+                self.raw(),
+                morse_code.as_ptr(),
+                bindings::TTY_NORMAL as c_char,
+                morse_code.len(),
+            );
+            bindings::tty_flip_buffer_push(self.raw());
+        }
+        Ok(written)
+    }
+}
+
+/// Open a port
+///
+/// # Arguments:
+/// * tty: The tty of the port.
+/// * file: The opened file.
+///
+/// # Returns:
+/// 0 on success, otherwise a negative error code returned by tty_port_open.
+unsafe extern "C" fn open(tty: *mut bindings::tty_struct, file: *mut bindings::file) -> c_int {
+    // SAFETY: The tty core only passes ttys of the driver.
+    let port = unsafe { Port::from_tty(tty) };
+    port.throttled.store(false, Ordering::Relaxed);
+    // SAFETY: Arguments are passed on as given by the tty core.
+    unsafe { bindings::tty_port_open(port.raw(), tty, file) }
+}
+
+/// Close a port
+///
+/// # Arguments:
+/// * tty: The tty of the port.
+/// * file: The closed file.
+unsafe extern "C" fn close(tty: *mut bindings::tty_struct, file: *mut bindings::file) {
+    // SAFETY: The tty core only passes ttys of the driver.
+    let port = unsafe { Port::from_tty(tty) };
+    // SAFETY: Arguments are passed on as given by the tty core.
+    unsafe { bindings::tty_port_close(port.raw(), tty, file) };
+}
+
+/// Hang up a port
+///
+/// # Arguments:
+/// * tty: The tty of the port.
+unsafe extern "C" fn hangup(tty: *mut bindings::tty_struct) {
+    // SAFETY: The tty core only passes ttys of the driver.
+    let port = unsafe { Port::from_tty(tty) };
+    // SAFETY: The port is initialized.
+    unsafe { bindings::tty_port_hangup(port.raw()) };
+}
+
+/// Write text into a port
+///
+/// # Arguments:
+/// * tty: The tty of the port.
+/// * buf: The text to write.
+/// * count: The number of bytes in buf.
+///
+/// # Returns:
+/// The number of bytes written on success, otherwise a negative error code. See Port::write.
+unsafe extern "C" fn write(tty: *mut bindings::tty_struct, buf: *const u8, count: c_int) -> c_int {
+    // SAFETY: The tty core only passes ttys of the driver.
+    let port = unsafe { Port::from_tty(tty) };
+    // SAFETY: The tty core passes a buffer containing count bytes.
+    let bytes = unsafe { core::slice::from_raw_parts(buf, count as usize) };
+    match port.write(bytes) {
+        Ok(written) => written as c_int,
+        Err(errno) => errno.to_kernel_errno(),
+    }
+}
+
+/// Get the number of bytes a port accepts
+///
+/// # Arguments:
+/// * tty: The tty of the port.
+///
+/// # Returns:
+/// 0 if the line discipline stopped receiving, otherwise the number of bytes that are
+/// converted into morse code fitting into the receive buffer for sure.
+///
+/// # Notes:
+/// A write may convert fewer bytes, if CHUNK_SIZE is smaller than the receive buffer space.
+/// It converts at least one character, since a write starts without held back characters.
+unsafe extern "C" fn write_room(tty: *mut bindings::tty_struct) -> c_uint {
+    // SAFETY: The tty core only passes ttys of the driver.
+    let port = unsafe { Port::from_tty(tty) };
+    if port.throttled.load(Ordering::Relaxed) {
+        return 0;
+    }
+    // SAFETY: The port is initialized.
+    let space = unsafe { bindings::tty_buffer_space_avail(port.raw()) } as usize;
+    // The morse code of a byte of text takes at most MAX_MORSE_CODE_LEN bytes. The space
+    // reserved for characters held back by the compressor is bound by the same limit, since
+    // they are released at the end of each write.
+    (space / MAX_MORSE_CODE_LEN) as c_uint
+}
+
+/// Stop converting text, since the line discipline stopped receiving.
+///
+/// # Arguments:
+/// * tty: The tty of the port.
+unsafe extern "C" fn throttle(tty: *mut bindings::tty_struct) {
+    // SAFETY: The tty core only passes ttys of the driver.
+    let port = unsafe { Port::from_tty(tty) };
+    port.throttled.store(true, Ordering::Relaxed);
+}
+
+/// Resume converting text, since the line discipline receives again.
+///
+/// # Arguments:
+/// * tty: The tty of the port.
+unsafe extern "C" fn unthrottle(tty: *mut bindings::tty_struct) {
+    // SAFETY: The tty core only passes ttys of the driver.
+    let port = unsafe { Port::from_tty(tty) };
+    port.throttled.store(false, Ordering::Relaxed);
+    // SAFETY: Wakes up writers waiting for write room of tty.
+    unsafe { bindings::tty_wakeup(tty) };
+}
@@ -18,6 +18,7 @@ readonly BUILDROOT_HOST_BIN_DIR="${BUILDROOT_HOST_DIR}/bin"
 readonly BUILDROOT_CONFIG="${BUILDROOT_BUILD_DIR}/.config"
 readonly BUILDROOT_CUSTOM_CONFIG="${ENV_DIR}/buildroot.config"
 readonly BUILDROOT_ROOTFS_BINARY="${BUILDROOT_ARTIFACT_DIR}/rootfs.ext2"
+readonly BUILDROOT_CC="${BUILDROOT_HOST_BIN_DIR}/x86_64-linux-gcc"
 
 # Buildroot: other
 declare BUILDROOT_MAKE_OPTS
@@ -27,6 +28,7 @@ readonly BUILDROOT_MAKE_OPTS
 # Linux: paths
 readonly LINUX_DIR="${ENV_DIR}/linux"
 readonly LINUX_BUILD_DIR="${BUILD_DIR}/linux"
+readonly LINUX_SELFTEST_DIR="${LINUX_DIR}/tools/testing/selftests"
 
 # Linux: files
 readonly LINUX_CONFIG="${LINUX_BUILD_DIR}/.config"
@@ -297,6 +299,7 @@ export BUILDROOT_HOST_BIN_DIR
 export BUILDROOT_CONFIG
 export BUILDROOT_CUSTOM_CONFIG
 export BUILDROOT_ROOTFS_BINARY
+export BUILDROOT_CC
 
 # Buildroot: other
 export BUILDROOT_MAKE_OPTS
@@ -304,6 +307,7 @@ export BUILDROOT_MAKE_OPTS
 # Linux: paths
 export LINUX_DIR
 export LINUX_BUILD_DIR
+export LINUX_SELFTEST_DIR
 
 # Linux: files
 export LINUX_CONFIG
@@ -0,0 +1,48 @@
+#!/bin/bash
+
+set -o errexit -o pipefail -o nounset
+source "scripts/common.sh"
+preamble
+abort_if_buildroot_was_not_built
+abort_if_linux_was_not_built
+
+# Sanity checks
+if [ "${1:-"NO_PARAM"}" = "NO_PARAM" ]
+then
+    echo "Mandatory parameter missing. Call according the following scheme:"
+    echo "./scripts/selftest_module.sh <module name>"
+    exit 1
+fi
+
+readonly MOD_NAME="${1}"
+readonly MOD_SELFTEST_DIR="${MODULE_DIR}/${MOD_NAME}/selftests"
+readonly MOD_SELFTEST_BUILD_DIR="${MODULE_BUILD_DIR}/${MOD_NAME}/selftests"
+
+if [ ! -d "${MOD_SELFTEST_DIR}" ]
+then
+    echo "Selftests not found at '${MOD_SELFTEST_DIR}'."
+    exit 1
+fi
+
+# Each C file under selftests is a test program, reporting its results in TAP format.
+echo "Build selftests of module '${MOD_NAME}'"
+mkdir -p "${MOD_SELFTEST_BUILD_DIR}"
+for SOURCE in "${MOD_SELFTEST_DIR}"/*.c
+do
+    "${BUILDROOT_CC}" -Wall -O2 -I"${LINUX_SELFTEST_DIR}" \
+        -o "${MOD_SELFTEST_BUILD_DIR}/$(basename "${SOURCE}" .c)" "${SOURCE}"
+done
+
+make -s -C "${BASE_DIR}" MODULE_NAME="${MOD_NAME}" load_module
+
+echo "Deploy selftests of module '${MOD_NAME}'"
+# shellcheck disable=SC2086 # Deliberate word splitting
+sshpass -f "${SSH_PASSWORD_FILE}" scp ${SCP_OPTS} "${MOD_SELFTEST_BUILD_DIR}"/* "${SSH_TARGET}:~"
+
+# A test program exits with a non-zero code, if one of its tests failed.
+for TEST in "${MOD_SELFTEST_BUILD_DIR}"/*
+do
+    echo "Run selftest '$(basename "${TEST}")'"
+    # shellcheck disable=SC2086 # Deliberate word splitting
+    sshpass -f "${SSH_PASSWORD_FILE}" ssh ${SSH_OPTS} "./$(basename "${TEST}")"
+done
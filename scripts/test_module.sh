@@ -0,0 +1,27 @@
+#!/bin/bash
+
+set -o errexit -o pipefail -o nounset
+source "scripts/common.sh"
+preamble
+abort_if_linux_was_not_built
+
+# Sanity checks
+if [ "${1:-"NO_PARAM"}" = "NO_PARAM" ]
+then
+    echo "Mandatory parameter missing. Call according the following scheme:"
+    echo "./scripts/test_module.sh <module name>"
+    exit 1
+fi
+
+readonly MOD_NAME="${1}"
+readonly KUNIT_RESULTS="/sys/kernel/debug/kunit/${MOD_NAME}/results"
+readonly KUNIT_TOOL="${LINUX_DIR}/tools/testing/kunit/kunit.py"
+
+# KUnit suites of a module run on loading it.
+make -s -C "${BASE_DIR}" MODULE_NAME="${MOD_NAME}" load_module
+
+echo "Evaluate KUnit results of module '${MOD_NAME}'"
+# shellcheck disable=SC2086 # Deliberate word splitting
+sshpass -f "${SSH_PASSWORD_FILE}" ssh ${SSH_OPTS} \
+    "mountpoint -q /sys/kernel/debug || mount -t debugfs none /sys/kernel/debug; \
+     cat ${KUNIT_RESULTS}" | "${KUNIT_TOOL}" parse